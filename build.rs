@@ -0,0 +1,24 @@
+// Provides the snapshot of the bundled-data feature, the committed data/bundled_holidays.json. The build never
+// uses the network, a snapshot without holidays fails it instead of building a binary without them.
+const SNAPSHOT_FILE: &str = "data/bundled_holidays.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SNAPSHOT_FILE);
+    #[cfg(feature = "bundled-data")]
+    bundle();
+}
+
+#[cfg(feature = "bundled-data")]
+fn bundle() {
+    let out_file = std::path::PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR")).join("bundled_holidays.json");
+    let content = std::fs::read_to_string(SNAPSHOT_FILE).unwrap_or_else(|err| panic!("{} can't be read: {}", SNAPSHOT_FILE, err));
+    let snapshot: serde_json::Value =
+        serde_json::from_str(&content).unwrap_or_else(|err| panic!("{} is not valid JSON: {}", SNAPSHOT_FILE, err));
+    if snapshot["data"].as_array().is_none_or(Vec::is_empty) {
+        panic!(
+            "{} holds no holidays. Write it with `cargo run -- bundle-data > {}` on a machine with a connection first.",
+            SNAPSHOT_FILE, SNAPSHOT_FILE
+        );
+    }
+    std::fs::write(&out_file, content).unwrap_or_else(|err| panic!("{} can't be written: {}", out_file.display(), err));
+}
@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::info::CountryInfo;
+use crate::{cache, CachedData, CachedLongWeekends, CountriesCache, FullCache, Holiday, Validators, WorldwideCache};
+
+// First bytes of a cache in --cache-format binary, followed by the cache version as 4 bytes little endian
+pub const BINARY_MAGIC: &[u8; 8] = b"GHCACHE\0";
+
+// postcard stores the fields one after another without their names, so it can't skip fields or flatten
+// them like the JSON cache does. The holidays, which are almost all of the cache, are copied into these
+// plain structs. The rest of the cache is small and only read by some subcommands, it is kept as JSON.
+#[derive(Serialize, Deserialize)]
+struct BinaryCache {
+    date: NaiveDate,
+    data: Vec<BinaryEntry>,
+    rest: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryEntry {
+    provider: String,
+    country_code: String,
+    year: i32,
+    date: NaiveDate,
+    fetched_at: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    last_accessed: Option<String>,
+    holidays: Vec<BinaryHoliday>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryHoliday {
+    date: NaiveDate,
+    local_name: Option<String>,
+    name: String,
+    counties: Option<Vec<String>>,
+    global: Option<bool>,
+    fixed: Option<bool>,
+    types: Vec<String>,
+    observed: Option<NaiveDate>,
+}
+
+// The parts of the cache besides the holidays
+#[derive(Deserialize)]
+struct CacheRest {
+    #[serde(default)]
+    worldwide: Option<WorldwideCache>,
+    #[serde(default)]
+    countries: Option<CountriesCache>,
+    #[serde(default)]
+    country_info: BTreeMap<String, CountryInfo>,
+    #[serde(default)]
+    long_weekends: Vec<CachedLongWeekends>,
+}
+
+pub fn encode(full_cache: &FullCache) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let rest = serde_json::json!({
+        "worldwide": full_cache.worldwide,
+        "countries": full_cache.countries,
+        "country_info": full_cache.country_info,
+        "long_weekends": full_cache.long_weekends,
+    });
+    let binary = BinaryCache {
+        date: full_cache.date,
+        data: full_cache.data.sorted().into_iter().map(BinaryEntry::from).collect(),
+        rest: rest.to_string(),
+    };
+    let mut content = BINARY_MAGIC.to_vec();
+    content.extend((full_cache.version as u32).to_le_bytes());
+    content.extend(postcard::to_allocvec(&binary)?);
+    Ok(content)
+}
+
+// None when the content is not a binary cache
+pub fn version(content: &[u8]) -> Option<Result<u64, String>> {
+    let header = content.strip_prefix(BINARY_MAGIC)?;
+    Some(
+        header
+            .get(..4)
+            .map(|version| u32::from_le_bytes([version[0], version[1], version[2], version[3]]) as u64)
+            .ok_or("the binary cache has no version".to_string()),
+    )
+}
+
+// The version was checked before, there are no older binary versions to migrate
+pub fn decode(content: &[u8]) -> Result<FullCache, String> {
+    let body = content.get(BINARY_MAGIC.len() + 4..).ok_or("the binary cache is empty")?;
+    let binary: BinaryCache = postcard::from_bytes(body).map_err(|err| format!("the binary cache could not be read: {}", err))?;
+    let rest: CacheRest = serde_json::from_str(&binary.rest).map_err(|err| err.to_string())?;
+    let mut data = cache::CacheEntries::default();
+    for entry in binary.data {
+        let entry = CachedData::from(entry);
+        data.insert(entry.key(), entry);
+    }
+    Ok(FullCache {
+        version: cache::CACHE_VERSION,
+        date: binary.date,
+        data,
+        worldwide: rest.worldwide,
+        countries: rest.countries,
+        country_info: rest.country_info,
+        long_weekends: rest.long_weekends,
+    })
+}
+
+impl From<&CachedData> for BinaryEntry {
+    fn from(data: &CachedData) -> BinaryEntry {
+        BinaryEntry {
+            provider: data.provider.clone(),
+            country_code: data.country_code.clone(),
+            year: data.year,
+            date: data.date,
+            fetched_at: data.fetched_at.clone(),
+            etag: data.validators.etag.clone(),
+            last_modified: data.validators.last_modified.clone(),
+            last_accessed: data.last_accessed.clone(),
+            holidays: data.holidays.iter().map(BinaryHoliday::from).collect(),
+        }
+    }
+}
+
+impl From<BinaryEntry> for CachedData {
+    fn from(entry: BinaryEntry) -> CachedData {
+        CachedData {
+            provider: entry.provider,
+            country_code: entry.country_code,
+            year: entry.year,
+            date: entry.date,
+            fetched_at: entry.fetched_at,
+            validators: Validators { etag: entry.etag, last_modified: entry.last_modified },
+            last_accessed: entry.last_accessed,
+            holidays: entry.holidays.into_iter().map(Holiday::from).collect(),
+        }
+    }
+}
+
+impl From<&Holiday> for BinaryHoliday {
+    fn from(holiday: &Holiday) -> BinaryHoliday {
+        BinaryHoliday {
+            date: holiday.date,
+            local_name: holiday.local_name.clone(),
+            name: holiday.name.clone(),
+            counties: holiday.counties.clone(),
+            global: holiday.global,
+            fixed: holiday.fixed,
+            types: holiday.types.clone(),
+            observed: holiday.observed,
+        }
+    }
+}
+
+impl From<BinaryHoliday> for Holiday {
+    fn from(holiday: BinaryHoliday) -> Holiday {
+        Holiday {
+            date: holiday.date,
+            local_name: holiday.local_name,
+            name: holiday.name,
+            counties: holiday.counties,
+            global: holiday.global,
+            fixed: holiday.fixed,
+            types: holiday.types,
+            observed: holiday.observed,
+        }
+    }
+}
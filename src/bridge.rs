@@ -0,0 +1,130 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashSet;
+
+// A run of consecutive days off, both ends inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeDays {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl FreeDays {
+    pub fn length(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+}
+
+// Taking `bridge_day` off joins the holiday with a weekend or another holiday
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeDay {
+    pub bridge_day: NaiveDate,
+    pub free_days: FreeDays,
+}
+
+fn is_day_off(date: NaiveDate, off_days: &HashSet<NaiveDate>) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun) || off_days.contains(&date)
+}
+
+// The weekend and holidays directly around `date`, so Dec 25 and 26 end up in the same block
+pub fn free_days_around(date: NaiveDate, off_days: &HashSet<NaiveDate>) -> FreeDays {
+    let mut start = date;
+    while is_day_off(start - Duration::days(1), off_days) {
+        start -= Duration::days(1);
+    }
+    let mut end = date;
+    while is_day_off(end + Duration::days(1), off_days) {
+        end += Duration::days(1);
+    }
+    FreeDays { start, end }
+}
+
+// A single working day next to the free days that connects them to further free days,
+// e.g. the Friday after a Thursday holiday. The longer result wins when both sides qualify.
+pub fn bridge_day(free_days: FreeDays, off_days: &HashSet<NaiveDate>) -> Option<BridgeDay> {
+    let before = free_days.start - Duration::days(1);
+    let after = free_days.end + Duration::days(1);
+
+    let bridge_before = is_day_off(before - Duration::days(1), off_days).then(|| BridgeDay {
+        bridge_day: before,
+        free_days: FreeDays {
+            start: free_days_around(before - Duration::days(1), off_days).start,
+            end: free_days.end,
+        },
+    });
+    let bridge_after = is_day_off(after + Duration::days(1), off_days).then(|| BridgeDay {
+        bridge_day: after,
+        free_days: FreeDays {
+            start: free_days.start,
+            end: free_days_around(after + Duration::days(1), off_days).end,
+        },
+    });
+
+    match (bridge_before, bridge_after) {
+        (Some(before), Some(after)) if after.free_days.length() > before.free_days.length() => Some(after),
+        (Some(before), _) => Some(before),
+        (None, after) => after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn free_days(start: NaiveDate, end: NaiveDate) -> FreeDays {
+        FreeDays { start, end }
+    }
+
+    #[test]
+    fn thursday_at_a_month_end_is_bridged_into_the_next_month() {
+        let off_days = HashSet::from([date(2026, 4, 30)]);
+        let around = free_days_around(date(2026, 4, 30), &off_days);
+        assert_eq!(around, free_days(date(2026, 4, 30), date(2026, 4, 30)));
+        let bridge = bridge_day(around, &off_days).unwrap();
+        assert_eq!(bridge.bridge_day, date(2026, 5, 1));
+        assert_eq!(bridge.free_days, free_days(date(2026, 4, 30), date(2026, 5, 3)));
+        assert_eq!(bridge.free_days.length(), 4);
+
+        // With a holiday on the Friday as well there is nothing left to bridge
+        let off_days = HashSet::from([date(2026, 4, 30), date(2026, 5, 1)]);
+        let around = free_days_around(date(2026, 4, 30), &off_days);
+        assert_eq!(around, free_days(date(2026, 4, 30), date(2026, 5, 3)));
+        assert_eq!(bridge_day(around, &off_days), None);
+    }
+
+    #[test]
+    fn christmas_days_before_a_weekend_are_one_block() {
+        // Thursday and Friday
+        let off_days = HashSet::from([date(2025, 12, 25), date(2025, 12, 26)]);
+        let around = free_days_around(date(2025, 12, 25), &off_days);
+        assert_eq!(around, free_days(date(2025, 12, 25), date(2025, 12, 28)));
+        assert_eq!(free_days_around(date(2025, 12, 26), &off_days), around);
+        assert_eq!(bridge_day(around, &off_days), None);
+    }
+
+    #[test]
+    fn free_days_continue_into_the_next_year() {
+        // Saturday, Sunday and the Monday holiday
+        let off_days = HashSet::from([date(2023, 1, 2)]);
+        assert_eq!(free_days_around(date(2022, 12, 31), &off_days), free_days(date(2022, 12, 31), date(2023, 1, 2)));
+        assert_eq!(free_days_around(date(2023, 1, 2), &off_days), free_days(date(2022, 12, 31), date(2023, 1, 2)));
+
+        // New Year on a Tuesday is bridged with the Monday before
+        let off_days = HashSet::from([date(2019, 1, 1)]);
+        let bridge = bridge_day(free_days_around(date(2019, 1, 1), &off_days), &off_days).unwrap();
+        assert_eq!(bridge.bridge_day, date(2018, 12, 31));
+        assert_eq!(bridge.free_days, free_days(date(2018, 12, 29), date(2019, 1, 1)));
+    }
+
+    #[test]
+    fn the_longer_side_is_bridged() {
+        // A Tuesday holiday with Thursday and Friday off: the Wednesday joins more days than the Monday
+        let off_days = HashSet::from([date(2026, 5, 12), date(2026, 5, 14), date(2026, 5, 15)]);
+        let bridge = bridge_day(free_days_around(date(2026, 5, 12), &off_days), &off_days).unwrap();
+        assert_eq!(bridge.bridge_day, date(2026, 5, 13));
+        assert_eq!(bridge.free_days, free_days(date(2026, 5, 12), date(2026, 5, 17)));
+    }
+}
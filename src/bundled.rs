@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::CachedData;
+
+// A snapshot of the holidays of every country, written by the bundle-data command into
+// data/bundled_holidays.json and built into the binary with the bundled-data feature.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundledData {
+    pub generated_at: Option<String>, // The day the snapshot was taken
+    pub data: Vec<CachedData>,        // Entries in the shape of the cache, so the cache lookup works on them
+}
+
+#[cfg(feature = "bundled-data")]
+pub fn bundled_data() -> Option<&'static BundledData> {
+    static DATA: std::sync::OnceLock<Option<BundledData>> = std::sync::OnceLock::new();
+    DATA.get_or_init(|| serde_json::from_str(include_str!(concat!(env!("OUT_DIR"), "/bundled_holidays.json"))).ok())
+        .as_ref()
+        .filter(|bundled| !bundled.data.is_empty())
+}
+
+// Builds without the feature have no snapshot
+#[cfg(not(feature = "bundled-data"))]
+pub fn bundled_data() -> Option<&'static BundledData> {
+    None
+}
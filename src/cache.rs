@@ -0,0 +1,1024 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::binary_cache::{self, BINARY_MAGIC};
+use crate::{
+    cache_dir, cache_file, cache_format, cache_ttl, default_cache_files, exit_with_error, max_cache_entries, handle_file_error, print_info,
+    print_verbose, try_write_file_atomically, write_file_atomically, CacheFormat, CachedData, CommandFormat, FullCache, Holiday,
+    CACHE_FILE, COMPRESS_CACHE, DEFAULT_CACHE_FILE,
+};
+
+pub const CACHE_VERSION: u64 = 2; // Version of the cache format, raised with every change older versions can't read
+const LOCK_TIMEOUT_SECS: u64 = 10; // How long a write waits for another process to finish its write
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b]; // First bytes of a gzip stream
+
+// Provider cache key, country code and year
+pub(crate) type CacheKey = (String, String, i32);
+
+// The cached holidays by their key. The file has them as a list in the order of the keys, the same as before.
+#[derive(Debug, Default)]
+pub(crate) struct CacheEntries(HashMap<CacheKey, CachedData>);
+
+impl CacheEntries {
+    pub fn sorted(&self) -> Vec<&CachedData> {
+        let mut entries: Vec<&CachedData> = self.0.values().collect();
+        entries.sort_by(|a, b| (&a.provider, &a.country_code, a.year).cmp(&(&b.provider, &b.country_code, b.year)));
+        entries
+    }
+
+    pub fn into_sorted(self) -> Vec<CachedData> {
+        let mut entries: Vec<CachedData> = self.0.into_values().collect();
+        entries.sort_by(|a, b| (&a.provider, &a.country_code, a.year).cmp(&(&b.provider, &b.country_code, b.year)));
+        entries
+    }
+}
+
+impl Deref for CacheEntries {
+    type Target = HashMap<CacheKey, CachedData>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for CacheEntries {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Serialize for CacheEntries {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.sorted().serialize(serializer)
+    }
+}
+
+// Of several entries with the same key, which older versions could write, the last one is used
+impl<'de> Deserialize<'de> for CacheEntries {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<CachedData>::deserialize(deserializer)?;
+        Ok(CacheEntries(entries.into_iter().map(|data| (data.key(), data)).collect()))
+    }
+}
+
+// The cache as it was last read or written in this run, so it is parsed only once
+static LOADED: Mutex<Option<Arc<FullCache>>> = Mutex::new(None);
+
+fn remember(full_cache: Arc<FullCache>) {
+    *LOADED.lock().unwrap_or_else(|err| err.into_inner()) = Some(full_cache);
+}
+
+// The cache that is there, the file of the current --compress-cache and --cache-format settings first
+fn existing_cache_file() -> Option<PathBuf> {
+    std::iter::once(cache_file()).chain(other_cache_files()).find(|path| path.exists())
+}
+
+// The files of the other --compress-cache and --cache-format settings, a chosen cache file has none
+fn other_cache_files() -> Vec<PathBuf> {
+    if CACHE_FILE.get().is_some() {
+        return Vec::new();
+    }
+    let cache_file = cache_file();
+    default_cache_files().into_iter().filter(|path| *path != cache_file).collect()
+}
+
+// Compressed and plain caches are told apart by their first bytes, not by the file name
+fn decode(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut content = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut content).map_err(|err| err.to_string())?;
+        Ok(content)
+    } else {
+        Ok(bytes)
+    }
+}
+
+fn compress(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    encoder.finish()
+}
+
+// None when there is no cache file yet. Together with the version the cache was written in.
+fn read_cache() -> Result<Option<(FullCache, u64)>, String> {
+    let Some(path) = existing_cache_file() else {
+        return Ok(None);
+    };
+    let content = decode(fs::read(&path).map_err(|err| err.to_string())?)?;
+    let version = content_version(&content)?;
+    if version > CACHE_VERSION {
+        // A newer version of get_holiday wrote it, its cache is kept for that version
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        // The version goes after the first part of the name, e.g. holidays_cache.v3.json
+        let backup_name = match file_name.split_once('.') {
+            Some((stem, extension)) => format!("{}.v{}.{}", stem, version, extension),
+            None => format!("{}.v{}", file_name, version),
+        };
+        let backup_path = path.with_file_name(backup_name);
+        fs::rename(&path, &backup_path).map_err(|err| err.to_string())?;
+        print_info(&format!(
+            "Warning: The cache was written by a newer version of get_holiday (cache version {}). It was moved to {} and a new cache is started.",
+            version,
+            backup_path.display()
+        ));
+        return Ok(None);
+    }
+    Ok(Some((parse_cache(&content, version)?, version)))
+}
+
+// Binary caches have the version in their header. JSON caches without a version were written before it was added.
+fn content_version(content: &[u8]) -> Result<u64, String> {
+    if let Some(version) = binary_cache::version(content) {
+        return version;
+    }
+    #[derive(Deserialize)]
+    struct Version {
+        version: Option<u64>,
+    }
+    let version: Version = serde_json::from_slice(content).map_err(|err| err.to_string())?;
+    Ok(version.version.unwrap_or(1))
+}
+
+// Either format is read whatever --cache-format says, so switching it keeps the cached holidays
+pub(crate) fn parse_cache(content: &[u8], version: u64) -> Result<FullCache, String> {
+    if content.starts_with(BINARY_MAGIC) {
+        return binary_cache::decode(content);
+    }
+    // The error names the entry and the value, e.g. "data[3].holidays[0].date: invalid date '2026-02-30'"
+    if version < 2 {
+        let mut value: serde_json::Value = serde_json::from_slice(content).map_err(|err| err.to_string())?;
+        migrate_v1(&mut value);
+        return serde_path_to_error::deserialize(value).map_err(|err| err.to_string());
+    }
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(content)).map_err(|err| err.to_string())
+}
+
+pub(crate) fn encode_cache(full_cache: &FullCache, format: CacheFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    match format {
+        CacheFormat::Json => Ok(serde_json::to_vec(full_cache)?),
+        CacheFormat::Binary => binary_cache::encode(full_cache),
+    }
+}
+
+// Version 1 had no provider, all holidays came from Nager, and old entries only had the day they were written
+fn migrate_v1(value: &mut serde_json::Value) {
+    if let Some(entries) = value.get_mut("data").and_then(serde_json::Value::as_array_mut) {
+        for entry in entries.iter_mut().filter_map(serde_json::Value::as_object_mut) {
+            entry.entry("provider").or_insert_with(|| crate::provider::default_cache_key().into());
+            if !entry.get("fetched_at").is_some_and(serde_json::Value::is_string) {
+                if let Some(date) = entry.get("date").and_then(serde_json::Value::as_str) {
+                    let fetched_at = format!("{}T00:00:00Z", date);
+                    entry.insert("fetched_at".to_string(), fetched_at.into());
+                }
+            }
+        }
+    }
+    value["version"] = 2.into();
+}
+
+// For lookups that must not lock or write anything, e.g. the shell prompt. An older cache is migrated only in
+// memory, and a cache that can't be read is the same as none.
+pub fn peek_cache() -> Option<Arc<FullCache>> {
+    if let Some(full_cache) = LOADED.lock().unwrap_or_else(|err| err.into_inner()).clone() {
+        return Some(full_cache);
+    }
+    let content = decode(fs::read(existing_cache_file()?).ok()?).ok()?;
+    let version = content_version(&content).ok().filter(|version| *version <= CACHE_VERSION)?;
+    parse_cache(&content, version).ok().map(Arc::new)
+}
+
+// For lookups. An error means the cache can't be parsed, a migrated cache is written back once.
+pub fn load_cache() -> Result<Option<Arc<FullCache>>, String> {
+    if let Some(full_cache) = LOADED.lock().unwrap_or_else(|err| err.into_inner()).clone() {
+        return Ok(Some(full_cache));
+    }
+    let Some((full_cache, version)) = read_cache()? else {
+        return Ok(None);
+    };
+    if version < CACHE_VERSION {
+        print_verbose(&format!("Migrating the cache from version {} to {}.", version, CACHE_VERSION));
+        // Read again under the lock, so the migration is done in the same way
+        update_cache(chrono::Local::now().date_naive(), |_| {}).map_err(|err| err.to_string())?;
+    }
+    let full_cache = Arc::new(full_cache);
+    remember(full_cache.clone());
+    Ok(Some(full_cache))
+}
+
+// Readers don't lock, they always see a complete file because it is replaced in one rename
+fn write_cache(full_cache: &FullCache) -> Result<(), Box<dyn std::error::Error>> {
+    let content = cache_content(full_cache)?;
+    write_file_atomically(&cache_file(), true, |out| Ok(out.write_all(&content)?))?;
+    remove_other_cache_files();
+    Ok(())
+}
+
+fn cache_content(full_cache: &FullCache) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let content = encode_cache(full_cache, cache_format())?;
+    Ok(if COMPRESS_CACHE.load(Ordering::Relaxed) { compress(&content)? } else { content })
+}
+
+// The cache is only kept in one of the files
+fn remove_other_cache_files() {
+    for other_cache_file in other_cache_files().into_iter().filter(|path| path.exists()) {
+        let _ = fs::remove_file(other_cache_file);
+    }
+}
+
+// holidays_cache.json.lock for both default files, so switching --compress-cache doesn't bypass the lock
+fn lock_file() -> PathBuf {
+    match CACHE_FILE.get() {
+        Some(cache_file) => {
+            let mut path = cache_file.clone().into_os_string();
+            path.push(".lock");
+            PathBuf::from(path)
+        }
+        None => cache_dir().join(format!("{}.lock", DEFAULT_CACHE_FILE)),
+    }
+}
+
+// Held while the cache is read, changed and written, the lock is released when it is dropped
+pub struct CacheLock {
+    _file: fs::File,
+}
+
+// The lock is on a file next to the cache, because the cache itself is replaced on every write
+pub fn lock_cache() -> Result<CacheLock, Box<dyn std::error::Error>> {
+    try_lock_cache()?.ok_or_else(|| {
+        eprintln!(
+            "Error: The cache is locked by another process ({}). Try again when it is finished.",
+            lock_file().display()
+        );
+        exit_with_error();
+    })
+}
+
+// None when another process still holds the lock after LOCK_TIMEOUT_SECS
+fn try_lock_cache() -> Result<Option<CacheLock>, Box<dyn std::error::Error>> {
+    let path = lock_file();
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path).inspect_err(|err| {
+        handle_file_error(err, &path.display().to_string());
+    })?;
+    let started = Instant::now();
+    while file.try_lock_exclusive().is_err() {
+        if started.elapsed().as_secs() >= LOCK_TIMEOUT_SECS {
+            return Ok(None);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    Ok(Some(CacheLock { _file: file }))
+}
+
+// None when the lock is held by another process or the lock file can't be opened, without waiting
+fn lock_cache_now() -> Option<CacheLock> {
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(lock_file()).ok()?;
+    file.try_lock_exclusive().ok()?;
+    Some(CacheLock { _file: file })
+}
+
+// The cache is read again under the lock, so the writes of other processes since it was last read are kept
+pub fn update_cache(today: NaiveDate, update: impl FnOnce(&mut FullCache)) -> Result<(), Box<dyn std::error::Error>> {
+    let lock = lock_cache()?;
+    update_locked_cache(lock, today, update)
+}
+
+// Like update_cache, but a lock that is held too long is an error instead of the end of the run
+pub fn try_update_cache(today: NaiveDate, update: impl FnOnce(&mut FullCache)) -> Result<(), Box<dyn std::error::Error>> {
+    let lock = try_lock_cache()?.ok_or("the cache is locked by another process")?;
+    update_locked_cache(lock, today, update)
+}
+
+// For changes that only record something, like when an entry was used: skipped when another process holds
+// the lock, and a cache that can't be read or written is left as it is
+pub fn update_cache_if_unlocked(update: impl FnOnce(&mut FullCache)) {
+    let Some(_lock) = lock_cache_now() else {
+        return;
+    };
+    let Ok(Some((mut full_cache, _))) = read_cache() else {
+        return;
+    };
+    update(&mut full_cache);
+    evict(&mut full_cache);
+    let written = cache_content(&full_cache)
+        .and_then(|content| try_write_file_atomically(&cache_file(), |out| Ok(out.write_all(&content)?)));
+    if written.is_ok() {
+        remove_other_cache_files();
+        remember(Arc::new(full_cache));
+    }
+}
+
+fn update_locked_cache(_lock: CacheLock, today: NaiveDate, update: impl FnOnce(&mut FullCache)) -> Result<(), Box<dyn std::error::Error>> {
+    // A cache that can't be parsed is replaced
+    let mut full_cache = read_cache().ok().flatten().map(|(full_cache, _)| full_cache).unwrap_or_else(|| FullCache::new(today));
+    update(&mut full_cache);
+    evict(&mut full_cache);
+    write_cache(&full_cache)?;
+    remember(Arc::new(full_cache));
+    Ok(())
+}
+
+// Removes the least recently used holidays until at most max_cache_entries are left
+fn evict(full_cache: &mut FullCache) {
+    let max_entries = max_cache_entries();
+    if full_cache.data.len() <= max_entries {
+        return;
+    }
+    // Entries of unknown age go first
+    let mut entries: Vec<&CachedData> = full_cache.data.values().collect();
+    entries.sort_by_key(|data| std::cmp::Reverse(data.unused_for().unwrap_or(Duration::MAX)));
+    let evicted: Vec<CacheKey> = entries[..full_cache.data.len() - max_entries].iter().map(|data| data.key()).collect();
+    for data in evicted.iter().filter_map(|key| full_cache.data.remove(key)) {
+        print_verbose(&format!(
+            "Removed the holidays of {} {} ({}) from the cache, it keeps at most {} entries.",
+            data.country_code, data.year, data.provider, max_entries
+        ));
+    }
+}
+
+// Without a country the whole cache is emptied, a year narrows it down to the entries of that year
+pub fn clear_cache(country_code: Option<&str>, year: Option<i32>, today: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock_cache()?;
+    // The cache may still be in the file of another --compress-cache or --cache-format setting
+    let path = existing_cache_file().unwrap_or_else(cache_file);
+    let full_cache = match read_cache() {
+        Ok(Some((full_cache, _))) => full_cache,
+        Ok(None) => {
+            println!("There is no cache at {}, nothing to remove.", path.display());
+            return Ok(());
+        }
+        // A cache that can't be read is only replaced when everything is removed anyway
+        Err(_) if country_code.is_none() && year.is_none() => {
+            write_cache(&FullCache::new(today))?;
+            println!("Replaced the unreadable cache at {} with an empty one.", path.display());
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("Error: The cache at {} could not be read ({}). Use 'cache clear' to empty it.", path.display(), err);
+            exit_with_error();
+        }
+    };
+
+    let matches = |entry_country_code: &str, entry_year: i32| {
+        country_code.is_none_or(|country_code| country_code.eq_ignore_ascii_case(entry_country_code))
+            && year.is_none_or(|year| year == entry_year)
+    };
+    let mut kept = FullCache { date: full_cache.date, ..FullCache::new(today) };
+    let mut removed = Vec::new();
+    for data in full_cache.data.into_sorted() {
+        if matches(&data.country_code, data.year) {
+            removed.push(format!("the holidays of {} {} ({})", data.country_code, data.year, data.provider));
+        } else {
+            kept.data.insert(data.key(), data);
+        }
+    }
+    for cached in full_cache.long_weekends {
+        if matches(&cached.country_code, cached.year) {
+            removed.push(format!("the long weekends of {} {}", cached.country_code, cached.year));
+        } else {
+            kept.long_weekends.push(cached);
+        }
+    }
+    // The country information and the lists don't belong to a year
+    for (info_country_code, info) in full_cache.country_info {
+        if year.is_none() && country_code.is_none_or(|country_code| country_code.eq_ignore_ascii_case(&info_country_code)) {
+            removed.push(format!("the country information of {}", info_country_code));
+        } else {
+            kept.country_info.insert(info_country_code, info);
+        }
+    }
+    if country_code.is_none() && year.is_none() {
+        if full_cache.countries.is_some() {
+            removed.push("the list of available countries".to_string());
+        }
+        if full_cache.worldwide.is_some() {
+            removed.push("the worldwide holidays".to_string());
+        }
+    } else {
+        kept.countries = full_cache.countries;
+        kept.worldwide = full_cache.worldwide;
+    }
+
+    if removed.is_empty() {
+        println!("No matching entries in the cache at {}.", path.display());
+        return Ok(());
+    }
+    write_cache(&kept)?;
+    for entry in &removed {
+        println!("Removed {}.", entry);
+    }
+    println!("Removed {} entries from the cache at {}.", removed.len(), path.display());
+    Ok(())
+}
+
+// Sizes in bytes of the cache file and of its content without gzip
+struct CacheSize {
+    path: PathBuf,
+    size: u64,
+    json_size: u64,
+    compressed: bool,
+    format: CacheFormat,
+}
+
+// The cache file is only read, a missing file is listed as an empty cache
+fn read_cache_or_exit() -> (FullCache, CacheSize) {
+    let path = cache_file();
+    match read_cache() {
+        Ok(full_cache) => {
+            let full_cache = full_cache.map(|(full_cache, _)| full_cache);
+            let path = existing_cache_file().unwrap_or(path);
+            let bytes = fs::read(&path).unwrap_or_default();
+            let compressed = bytes.starts_with(&GZIP_MAGIC);
+            let content = decode(bytes.clone()).unwrap_or_default();
+            let size = CacheSize {
+                size: bytes.len() as u64,
+                compressed,
+                json_size: content.len() as u64,
+                format: if content.starts_with(BINARY_MAGIC) { CacheFormat::Binary } else { CacheFormat::Json },
+                path,
+            };
+            (full_cache.unwrap_or_else(|| FullCache::new(chrono::Local::now().date_naive())), size)
+        }
+        Err(err) => {
+            eprintln!("Error: The cache at {} could not be read ({}). Use 'cache clear' to empty it.", path.display(), err);
+            exit_with_error();
+        }
+    }
+}
+
+// "3d 4h", "5h 12m" or "40m"
+pub(crate) fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d {}h", age.num_days(), age.num_hours() % 24)
+    } else if age.num_hours() > 0 {
+        format!("{}h {}m", age.num_hours(), age.num_minutes() % 60)
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+#[derive(Serialize)]
+struct ListedEntry<'a> {
+    country: &'a str,
+    year: i32,
+    provider: &'a str,
+    holidays: usize,
+    fetched_at: Option<&'a str>, // Missing in entries written by older versions
+    age_seconds: Option<i64>,
+    fresh: bool,
+}
+
+#[derive(Serialize)]
+struct CacheList<'a> {
+    path: String,
+    size: u64, // Bytes
+    entries: Vec<ListedEntry<'a>>,
+}
+
+// One row per cached country and year
+pub fn write_cache_list(out: &mut dyn Write, format: CommandFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let (full_cache, size) = read_cache_or_exit();
+    let mut list = CacheList {
+        path: size.path.display().to_string(),
+        size: size.size,
+        entries: full_cache
+            .data
+            .values()
+            .map(|data| ListedEntry {
+                country: &data.country_code,
+                year: data.year,
+                provider: &data.provider,
+                holidays: data.holidays.len(),
+                fetched_at: data.fetched_at.as_deref(),
+                age_seconds: data.age().map(|age| age.num_seconds()),
+                fresh: data.is_fresh(),
+            })
+            .collect(),
+    };
+    list.entries.sort_by(|a, b| (a.country, a.year, a.provider).cmp(&(b.country, b.year, b.provider)));
+
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?,
+        CommandFormat::Text => {
+            if list.entries.is_empty() {
+                writeln!(out, "No holidays in the cache.")?;
+            } else {
+                let provider_width = list.entries.iter().map(|entry| entry.provider.len()).max().unwrap_or_default().max(8);
+                writeln!(out, "Country  Year  {:provider_width$}  Holidays  Fetched at            Age      Status", "Provider")?;
+                for entry in &list.entries {
+                    writeln!(
+                        out,
+                        "{:7}  {:4}  {:provider_width$}  {:>8}  {:20}  {:7}  {}",
+                        entry.country,
+                        entry.year,
+                        entry.provider,
+                        entry.holidays,
+                        entry.fetched_at.unwrap_or("-"),
+                        entry.age_seconds.map(|age| format_age(Duration::seconds(age))).unwrap_or("-".to_string()),
+                        if entry.fresh { "fresh" } else { "expired" }
+                    )?;
+                }
+            }
+            writeln!(out, "{} ({} bytes)", list.path, list.size)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CacheStats {
+    path: String,
+    size: u64, // Bytes
+    max_entries: usize,
+    format: CacheFormat,
+    compressed: bool,
+    json_size: u64, // Without gzip, also for the binary format
+    gzip_size: u64, // What the cache takes with --compress-cache
+    entries: usize,
+    fresh: usize,
+    expired: usize,
+    holidays: usize,
+    countries: Vec<String>,
+    years: Vec<i32>,
+    oldest_age_seconds: Option<i64>,
+    ttl_seconds: i64,
+    long_weekends: usize,
+    country_info: usize,
+    country_list: bool,
+    worldwide: bool,
+}
+
+// A summary for scripts that check whether the cache is warm
+pub fn write_cache_stats(out: &mut dyn Write, format: CommandFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let (full_cache, size) = read_cache_or_exit();
+    let fresh = full_cache.data.values().filter(|data| data.is_fresh()).count();
+    let gzip_size = if size.compressed {
+        size.size
+    } else {
+        compress(&encode_cache(&full_cache, size.format)?)?.len() as u64
+    };
+    let stats = CacheStats {
+        path: size.path.display().to_string(),
+        size: size.size,
+        max_entries: max_cache_entries(),
+        format: size.format,
+        compressed: size.compressed,
+        json_size: size.json_size,
+        gzip_size,
+        entries: full_cache.data.len(),
+        fresh,
+        expired: full_cache.data.len() - fresh,
+        holidays: full_cache.data.values().map(|data| data.holidays.len()).sum(),
+        countries: full_cache.data.values().map(|data| data.country_code.clone()).collect::<BTreeSet<_>>().into_iter().collect(),
+        years: full_cache.data.values().map(|data| data.year).collect::<BTreeSet<_>>().into_iter().collect(),
+        oldest_age_seconds: full_cache.data.values().filter_map(|data| data.age()).max().map(|age| age.num_seconds()),
+        ttl_seconds: cache_ttl().num_seconds(),
+        long_weekends: full_cache.long_weekends.len(),
+        country_info: full_cache.country_info.len(),
+        country_list: full_cache.countries.is_some(),
+        worldwide: full_cache.worldwide.is_some(),
+    };
+
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&stats)?)?,
+        CommandFormat::Text => {
+            writeln!(out, "Cache: {} ({} bytes)", stats.path, stats.size)?;
+            let format_name = match stats.format {
+                CacheFormat::Json => "JSON",
+                CacheFormat::Binary => "binary",
+            };
+            writeln!(out, "Format: {}", format_name)?;
+            let saved = 100u64.saturating_sub(stats.gzip_size * 100 / stats.json_size.max(1));
+            if stats.compressed {
+                writeln!(out, "Compressed: {} bytes of {} in {} bytes, {}% smaller", stats.json_size, format_name, stats.gzip_size, saved)?;
+            } else {
+                writeln!(out, "Compressed: no, it would take {} bytes with --compress-cache, {}% smaller", stats.gzip_size, saved)?;
+            }
+            writeln!(
+                out,
+                "Entries: {} of at most {} ({} fresh, {} expired after {})",
+                stats.entries,
+                stats.max_entries,
+                stats.fresh,
+                stats.expired,
+                format_age(cache_ttl())
+            )?;
+            writeln!(out, "Holidays: {}", stats.holidays)?;
+            let countries = if stats.countries.is_empty() { "-".to_string() } else { stats.countries.join(", ") };
+            writeln!(out, "Countries: {}", countries)?;
+            let years: Vec<String> = stats.years.iter().map(|year| year.to_string()).collect();
+            writeln!(out, "Years: {}", if years.is_empty() { "-".to_string() } else { years.join(", ") })?;
+            if let Some(oldest_age) = stats.oldest_age_seconds {
+                writeln!(out, "Oldest entry: {}", format_age(Duration::seconds(oldest_age)))?;
+            }
+            writeln!(out, "Long weekends: {}", stats.long_weekends)?;
+            writeln!(out, "Country information: {}", stats.country_info)?;
+            writeln!(out, "Country list: {}", if stats.country_list { "cached" } else { "not cached" })?;
+            writeln!(out, "Worldwide holidays: {}", if stats.worldwide { "cached" } else { "not cached" })?;
+        }
+    }
+    Ok(())
+}
+
+const EXPORT_FORMAT: &str = "get_holiday-cache"; // Tells an export apart from any other JSON file
+
+// The cached holidays in a file of their own, to seed the cache of another machine
+#[derive(Serialize, Deserialize)]
+struct CacheExport {
+    format: String, // EXPORT_FORMAT
+    version: u64,   // CACHE_VERSION of the entries
+    exported_at: String,
+    entries: Vec<CachedData>,
+}
+
+// stdout when no file is given
+pub fn export_cache(output_file: Option<&str>, create_dirs: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (full_cache, _) = read_cache_or_exit();
+    let export = CacheExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: CACHE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        entries: full_cache.data.into_sorted(),
+    };
+    match output_file.filter(|path| *path != "-") {
+        Some(path) => {
+            write_file_atomically(Path::new(path), create_dirs, |out| Ok(serde_json::to_writer_pretty(out, &export)?))?;
+            print_info(&format!("Exported {} entries to {}.", export.entries.len(), path));
+        }
+        None => writeln!(std::io::stdout().lock(), "{}", serde_json::to_string_pretty(&export)?)?,
+    }
+    Ok(())
+}
+
+// The whole file is checked before the cache is changed, so a bad file leaves the cache as it was
+fn read_export(path: &str) -> Result<CacheExport, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+    if value.get("format").and_then(serde_json::Value::as_str) != Some(EXPORT_FORMAT) {
+        return Err("the file was not written by 'cache export'".to_string());
+    }
+    let version = value.get("version").and_then(serde_json::Value::as_u64).unwrap_or_default();
+    if version != CACHE_VERSION {
+        return Err(format!("the file has cache version {}, this version of get_holiday reads version {}", version, CACHE_VERSION));
+    }
+    let mut export: CacheExport = serde_path_to_error::deserialize(value).map_err(|err| err.to_string())?;
+    // Kept in UTC like the times the cache writes itself, another machine may have written a different offset
+    for (index, entry) in export.entries.iter_mut().enumerate() {
+        if let Some(fetched_at) = &entry.fetched_at {
+            let fetched_at = chrono::DateTime::parse_from_rfc3339(fetched_at)
+                .map_err(|_| format!("entries[{}].fetched_at: invalid time", index))?;
+            entry.fetched_at = Some(fetched_at.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+        }
+    }
+    Ok(export)
+}
+
+// Entries of older versions without fetched_at count as the oldest
+fn fetched_time(entry: &CachedData) -> Option<chrono::DateTime<chrono::Utc>> {
+    let fetched_at = chrono::DateTime::parse_from_rfc3339(entry.fetched_at.as_deref()?).ok()?;
+    Some(fetched_at.with_timezone(&chrono::Utc))
+}
+
+// An entry that is in the cache already is only replaced when the imported one was fetched later
+pub fn import_cache(path: &str, today: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    let export = read_export(path).unwrap_or_else(|err| {
+        eprintln!("Error: Could not import '{}': {}", path, err);
+        exit_with_error();
+    });
+    let (mut imported, mut kept) = (0, 0);
+    update_cache(today, |full_cache| {
+        for entry in export.entries {
+            if full_cache.data.get(&entry.key()).is_some_and(|cached| fetched_time(cached) >= fetched_time(&entry)) {
+                kept += 1;
+                continue;
+            }
+            full_cache.data.insert(entry.key(), entry);
+            imported += 1;
+        }
+    })?;
+    println!(
+        "Imported {} entries into the cache at {}, kept {} cached entries that are as new or newer.",
+        imported,
+        cache_file().display(),
+        kept
+    );
+    Ok(())
+}
+
+// A generated cache like one of every country for a few years, each holiday with a local name, counties and a type
+// The country codes are AA to ZZ, so there are at most 676 countries
+fn benchmark_cache(countries: usize, years: i32) -> FullCache {
+    let today = chrono::Local::now().date_naive();
+    let mut full_cache = FullCache::new(today);
+    for index in 0..countries {
+        let country_code = format!("{}{}", (b'A' + (index / 26) as u8) as char, (b'A' + (index % 26) as u8) as char);
+        for year in today.year()..today.year() + years {
+            let holidays = (1..=15)
+                .map(|day| Holiday {
+                    date: NaiveDate::from_ymd_opt(year, (day % 12 + 1) as u32, day as u32).expect("the day exists in every month"),
+                    local_name: Some(format!("Feiertag {}", day)),
+                    name: format!("Holiday {}", day),
+                    counties: (day % 3 == 0).then(|| vec![format!("{}-A", country_code), format!("{}-B", country_code)]),
+                    global: Some(day % 3 != 0),
+                    fixed: Some(day % 2 == 0),
+                    types: vec!["Public".to_string()],
+                    observed: None,
+                })
+                .collect();
+            let entry = CachedData {
+                provider: crate::provider::default_cache_key(),
+                country_code: country_code.clone(),
+                year,
+                date: today,
+                fetched_at: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                validators: crate::Validators { etag: Some(format!("\"{}-{}\"", country_code, year)), last_modified: None },
+                last_accessed: None,
+                holidays,
+            };
+            full_cache.data.insert(entry.key(), entry);
+        }
+    }
+    full_cache
+}
+
+// Times the parsing of the same cache in both formats, the way it is read on every run
+pub fn run_benchmark(out: &mut dyn Write, countries: usize, years: i32, rounds: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let full_cache = benchmark_cache(countries, years);
+    let holidays: usize = full_cache.data.values().map(|data| data.holidays.len()).sum();
+    writeln!(out, "Cache with {} countries, {} years and {} holidays, median of {} rounds:", countries, years, holidays, rounds)?;
+    let mut json_time = None;
+    for (name, format) in [("JSON", CacheFormat::Json), ("Binary", CacheFormat::Binary)] {
+        let content = encode_cache(&full_cache, format)?;
+        let mut times = Vec::new();
+        for _ in 0..rounds {
+            let started = Instant::now();
+            let parsed_cache = parse_cache(&content, content_version(&content)?)?;
+            times.push(started.elapsed());
+            if parsed_cache.data.len() != full_cache.data.len() {
+                return Err(format!(
+                    "the {} format read back {} of {} entries",
+                    name,
+                    parsed_cache.data.len(),
+                    full_cache.data.len()
+                )
+                .into());
+            }
+        }
+        times.sort();
+        let median = times[times.len() / 2];
+        let speedup = json_time.map(|json_time: std::time::Duration| format!(", {:.1}x faster", json_time.as_secs_f64() / median.as_secs_f64()));
+        json_time.get_or_insert(median);
+        writeln!(
+            out,
+            "{:8}{:>9} bytes, parsed in {:.2} ms{}",
+            format!("{}:", name),
+            content.len(),
+            median.as_secs_f64() * 1000.0,
+            speedup.unwrap_or_default()
+        )?;
+    }
+
+    // Every entry looked up once, in the list the cache was before its entries were keyed and in the map
+    let keys: Vec<CacheKey> = full_cache.data.keys().cloned().collect();
+    let list = full_cache.data.sorted();
+    let mut scan_times = Vec::new();
+    let mut map_times = Vec::new();
+    for _ in 0..rounds {
+        let started = Instant::now();
+        let found = keys.iter().filter(|key| list.iter().any(|data| (&data.provider, &data.country_code, data.year) == (&key.0, &key.1, key.2))).count();
+        scan_times.push(started.elapsed());
+        let started = Instant::now();
+        let found_in_map = keys.iter().filter(|key| full_cache.data.contains_key(*key)).count();
+        map_times.push(started.elapsed());
+        if found != keys.len() || found_in_map != keys.len() {
+            return Err(format!("the lookups found {} and {} of {} entries", found, found_in_map, keys.len()).into());
+        }
+    }
+    scan_times.sort();
+    map_times.sort();
+    let (scan_time, map_time) = (scan_times[scan_times.len() / 2], map_times[map_times.len() / 2]);
+    writeln!(out, "{} lookups:", keys.len())?;
+    writeln!(out, "{:8}{:>9.3} ms", "List:", scan_time.as_secs_f64() * 1000.0)?;
+    writeln!(
+        out,
+        "{:8}{:>9.3} ms, {:.1}x faster",
+        "Map:",
+        map_time.as_secs_f64() * 1000.0,
+        scan_time.as_secs_f64() / map_time.as_secs_f64().max(f64::MIN_POSITIVE)
+    )?;
+    Ok(())
+}
+
+// The cache location is set once for the whole test binary, so the tests that use the cache file take turns.
+// Every test starts with an empty cache.
+#[cfg(test)]
+pub(crate) fn use_test_cache() -> std::sync::MutexGuard<'static, ()> {
+    static TEST_CACHE: Mutex<()> = Mutex::new(());
+    let guard = TEST_CACHE.lock().unwrap_or_else(|err| err.into_inner());
+    crate::CACHE_DIR.get_or_init(|| {
+        let directory = std::env::temp_dir().join(format!("get_holiday-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).expect("the temporary directory is writable");
+        directory
+    });
+    for path in default_cache_files() {
+        let _ = fs::remove_file(path);
+    }
+    forget_loaded_cache();
+    guard
+}
+
+// The next lookup reads the file again, like a new run
+#[cfg(test)]
+pub(crate) fn forget_loaded_cache() {
+    *LOADED.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+#[cfg(test)]
+pub(crate) fn test_entry(country_code: &str, year: i32, fetched_at: &str) -> CachedData {
+    CachedData {
+        provider: crate::provider::default_cache_key(),
+        country_code: country_code.to_string(),
+        year,
+        date: NaiveDate::from_ymd_opt(2026, 10, 14).unwrap(),
+        fetched_at: Some(fetched_at.to_string()),
+        validators: crate::Validators::default(),
+        last_accessed: None,
+        holidays: vec![Holiday {
+            date: NaiveDate::from_ymd_opt(year, 12, 25).unwrap(),
+            local_name: Some("Weihnachten".to_string()),
+            name: "Christmas Day".to_string(),
+            counties: None,
+            global: Some(true),
+            fixed: Some(true),
+            types: vec!["Public".to_string()],
+            observed: None,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    fn cached_entries() -> Vec<CachedData> {
+        read_cache().unwrap().map(|(full_cache, _)| full_cache.data.into_sorted()).unwrap_or_default()
+    }
+
+    fn write_export(entries: Vec<CachedData>) -> PathBuf {
+        let path = cache_dir().join("export.json");
+        let export = CacheExport {
+            format: EXPORT_FORMAT.to_string(),
+            version: CACHE_VERSION,
+            exported_at: "2026-10-14T12:00:00Z".to_string(),
+            entries,
+        };
+        fs::write(&path, serde_json::to_string(&export).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn version_1_cache_is_migrated() {
+        let content = include_bytes!("../tests/fixtures/cache_v1.json");
+        let version = content_version(content).unwrap();
+        assert_eq!(version, 1);
+
+        let full_cache = parse_cache(content, version).unwrap();
+        assert_eq!(full_cache.version, CACHE_VERSION);
+        let entries = full_cache.data.into_sorted();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.provider == crate::provider::default_cache_key()));
+        // Without fetched_at the day the entry was written is the fetch time
+        assert_eq!(entries[0].fetched_at.as_deref(), Some("2025-03-01T00:00:00Z"));
+        assert_eq!(entries[1].fetched_at.as_deref(), Some("2025-03-02T14:05:11Z"));
+        assert_eq!(entries[0].holidays.len(), 2);
+        assert_eq!(entries[0].holidays[1].counties.as_deref(), Some(&["DE-BE".to_string(), "DE-MV".to_string()][..]));
+        assert_eq!(entries[1].holidays[0].local_name.as_deref(), Some("Fête nationale"));
+    }
+
+    #[test]
+    fn migrated_cache_is_written_back() {
+        let _cache = use_test_cache();
+        fs::write(cache_file(), include_bytes!("../tests/fixtures/cache_v1.json")).unwrap();
+        let full_cache = load_cache().unwrap().unwrap();
+        assert_eq!(full_cache.data.len(), 2);
+        assert_eq!(content_version(&fs::read(cache_file()).unwrap()).unwrap(), CACHE_VERSION);
+    }
+
+    // Set for the copies of the test binary that concurrent_writers_keep_every_entry starts
+    const WRITER_ENV: &str = "GET_HOLIDAY_TEST_WRITER";
+    const WRITER_CACHE_DIR_ENV: &str = "GET_HOLIDAY_TEST_WRITER_CACHE_DIR";
+    const WRITERS: usize = 12;
+
+    // One of the writer processes, like a cron job. It does nothing in a normal test run.
+    #[test]
+    fn cache_writer_process() {
+        let (Ok(writer), Ok(directory)) = (std::env::var(WRITER_ENV), std::env::var(WRITER_CACHE_DIR_ENV)) else {
+            return;
+        };
+        crate::CACHE_DIR.set(PathBuf::from(directory)).unwrap();
+        for year in 2020..2025 {
+            let entry = test_entry(&format!("C{}", writer), year, "2026-10-14T10:00:00Z");
+            update_cache(today(), |full_cache| {
+                full_cache.data.insert(entry.key(), entry);
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn concurrent_writers_keep_every_entry() {
+        let _cache = use_test_cache();
+        let test_binary = std::env::current_exe().unwrap();
+        let writers: Vec<std::process::Child> = (0..WRITERS)
+            .map(|writer| {
+                std::process::Command::new(&test_binary)
+                    .args(["--exact", "cache::tests::cache_writer_process", "--quiet"])
+                    .env(WRITER_ENV, writer.to_string())
+                    .env(WRITER_CACHE_DIR_ENV, crate::cache_dir())
+                    .stdout(std::process::Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for mut writer in writers {
+            assert!(writer.wait().unwrap().success());
+        }
+
+        let keys: BTreeSet<(String, i32)> =
+            cached_entries().into_iter().map(|entry| (entry.country_code, entry.year)).collect();
+        let expected: BTreeSet<(String, i32)> =
+            (0..WRITERS).flat_map(|writer| (2020..2025).map(move |year| (format!("C{}", writer), year))).collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn export_clear_import_keeps_the_entries() {
+        let _cache = use_test_cache();
+        let entries = vec![test_entry("DE", 2026, "2026-10-01T08:00:00Z"), test_entry("FR", 2027, "2026-10-02T09:30:00Z")];
+        update_cache(today(), |full_cache| {
+            for entry in entries.clone() {
+                full_cache.data.insert(entry.key(), entry);
+            }
+        })
+        .unwrap();
+
+        let path = cache_dir().join("export.json");
+        export_cache(path.to_str(), false).unwrap();
+        clear_cache(None, None, today()).unwrap();
+        assert!(cached_entries().is_empty());
+
+        import_cache(path.to_str().unwrap(), today()).unwrap();
+        let imported = cached_entries();
+        assert_eq!(imported.len(), 2);
+        for (imported, entry) in imported.iter().zip(&entries) {
+            assert_eq!(imported.key(), entry.key());
+            assert_eq!(imported.fetched_at, entry.fetched_at);
+            assert_eq!(imported.holidays.len(), entry.holidays.len());
+            assert_eq!(imported.holidays[0].date, entry.holidays[0].date);
+        }
+    }
+
+    #[test]
+    fn import_compares_fetch_times_across_offsets() {
+        let _cache = use_test_cache();
+        update_cache(today(), |full_cache| {
+            for entry in [test_entry("DE", 2026, "2026-10-14T10:00:00Z"), test_entry("FR", 2026, "2026-10-14T10:00:00Z")] {
+                full_cache.data.insert(entry.key(), entry);
+            }
+        })
+        .unwrap();
+
+        // 09:00 UTC is older than the cached entry even though the text sorts later, 11:00 UTC is newer
+        let path = write_export(vec![
+            test_entry("DE", 2026, "2026-10-14T11:00:00+02:00"),
+            test_entry("FR", 2026, "2026-10-14T13:00:00+02:00"),
+        ]);
+        import_cache(path.to_str().unwrap(), today()).unwrap();
+        let fetched_at: Vec<Option<String>> = cached_entries().into_iter().map(|entry| entry.fetched_at).collect();
+        assert_eq!(fetched_at, [Some("2026-10-14T10:00:00Z".to_string()), Some("2026-10-14T11:00:00Z".to_string())]);
+    }
+
+    #[test]
+    fn benchmark_looks_up_every_entry() {
+        let mut out = Vec::new();
+        run_benchmark(&mut out, 100, 3, 1).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("300 lookups:\nList:"), "{}", out);
+        assert!(out.contains("Map:"), "{}", out);
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{CommandFormat, Holiday};
+
+// Holidays grouped by their date, several holidays can fall on the same day
+fn by_date(holidays: &[Holiday]) -> BTreeMap<NaiveDate, Vec<&Holiday>> {
+    let mut dates: BTreeMap<NaiveDate, Vec<&Holiday>> = BTreeMap::new();
+    for holiday in holidays {
+        dates.entry(holiday.date).or_default().push(holiday);
+    }
+    dates
+}
+
+fn names(holidays: &[&Holiday]) -> String {
+    holidays.iter().map(|holiday| holiday.name.as_str()).collect::<Vec<_>>().join(" / ")
+}
+
+// A date that is a holiday in both countries, the names usually differ
+#[derive(Serialize)]
+struct SharedDate<'a> {
+    date: String,
+    left: Vec<&'a Holiday>,
+    right: Vec<&'a Holiday>,
+}
+
+#[derive(Serialize)]
+struct Diff<'a> {
+    left: &'a str,
+    right: &'a str,
+    only_left: Vec<&'a Holiday>,
+    only_right: Vec<&'a Holiday>,
+    both: Vec<SharedDate<'a>>,
+}
+
+// Holidays are matched by date, because the same holiday has a different name in every country
+pub fn write_diff(
+    out: &mut dyn Write,
+    (left_code, left): (&str, &[Holiday]),
+    (right_code, right): (&str, &[Holiday]),
+    format: CommandFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let left_dates = by_date(left);
+    let right_dates = by_date(right);
+    let diff = Diff {
+        left: left_code,
+        right: right_code,
+        only_left: left_dates
+            .iter()
+            .filter(|(date, _)| !right_dates.contains_key(date))
+            .flat_map(|(_, holidays)| holidays.iter().copied())
+            .collect(),
+        only_right: right_dates
+            .iter()
+            .filter(|(date, _)| !left_dates.contains_key(date))
+            .flat_map(|(_, holidays)| holidays.iter().copied())
+            .collect(),
+        both: left_dates
+            .iter()
+            .filter_map(|(date, left)| {
+                right_dates.get(date).map(|right| SharedDate { date: date.to_string(), left: left.clone(), right: right.clone() })
+            })
+            .collect(),
+    };
+
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&diff)?)?,
+        CommandFormat::Text => {
+            for (country_code, holidays) in [(left_code, &diff.only_left), (right_code, &diff.only_right)] {
+                writeln!(out, "Only in {}:", country_code)?;
+                if holidays.is_empty() {
+                    writeln!(out, "  — none —")?;
+                }
+                for holiday in holidays {
+                    writeln!(out, "  {}  {}", holiday.date, holiday.name)?;
+                }
+            }
+            writeln!(out, "In both {} and {}:", left_code, right_code)?;
+            if diff.both.is_empty() {
+                writeln!(out, "  — none —")?;
+            }
+            for shared in &diff.both {
+                writeln!(out, "  {}  {} | {}", shared.date, names(&shared.left), names(&shared.right))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A date that is a holiday in enough of the compared countries
+#[derive(Serialize)]
+struct CommonDate<'a> {
+    date: String,
+    holidays: BTreeMap<&'a str, Vec<&'a Holiday>>, // By country code, only the countries where the date is a holiday
+}
+
+// The holidays of every country where a date is a holiday
+type DateCountries<'a> = Vec<(&'a str, Vec<&'a Holiday>)>;
+
+// The dates that are holidays in at least `at_least` of the countries, in date order
+fn common_dates(countries: &[(String, Vec<Holiday>)], at_least: usize) -> Vec<(NaiveDate, DateCountries<'_>)> {
+    let mut dates: BTreeMap<NaiveDate, DateCountries> = BTreeMap::new();
+    for (country_code, holidays) in countries {
+        for (date, holidays) in by_date(holidays) {
+            dates.entry(date).or_default().push((country_code, holidays));
+        }
+    }
+    dates.into_iter().filter(|(_, countries)| countries.len() >= at_least).collect()
+}
+
+pub fn write_common(
+    out: &mut dyn Write,
+    countries: &[(String, Vec<Holiday>)],
+    at_least: usize,
+    format: CommandFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let common_dates = common_dates(countries, at_least);
+
+    match format {
+        CommandFormat::Json => {
+            let common_dates: Vec<CommonDate> = common_dates
+                .iter()
+                .map(|(date, countries)| CommonDate {
+                    date: date.to_string(),
+                    holidays: countries.iter().map(|(country_code, holidays)| (*country_code, holidays.clone())).collect(),
+                })
+                .collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&common_dates)?)?;
+        }
+        CommandFormat::Text => {
+            if common_dates.is_empty() {
+                writeln!(out, "No date is a holiday in at least {} of the {} countries.", at_least, countries.len())?;
+            }
+            for (date, date_countries) in &common_dates {
+                let country_names: Vec<String> = date_countries
+                    .iter()
+                    .map(|(country_code, holidays)| format!("{}: {}", country_code, names(holidays)))
+                    .collect();
+                // The share is only interesting when not every country has to match
+                let share = if at_least < countries.len() {
+                    format!(" ({} of {})", date_countries.len(), countries.len())
+                } else {
+                    String::new()
+                };
+                writeln!(out, "{}{}  {}", date, share, country_names.join(", "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holiday(date: &str, name: &str) -> Holiday {
+        Holiday {
+            date: date.parse().unwrap(),
+            local_name: None,
+            name: name.to_string(),
+            counties: None,
+            global: Some(true),
+            fixed: None,
+            types: vec!["Public".to_string()],
+            observed: None,
+        }
+    }
+
+    fn countries() -> Vec<(String, Vec<Holiday>)> {
+        vec![
+            (
+                "DE".to_string(),
+                vec![holiday("2026-01-01", "New Year's Day"), holiday("2026-05-01", "Labour Day"), holiday("2026-12-25", "Christmas Day")],
+            ),
+            (
+                "NL".to_string(),
+                vec![holiday("2026-01-01", "New Year's Day"), holiday("2026-04-27", "King's Day"), holiday("2026-12-25", "Christmas Day")],
+            ),
+            (
+                "TR".to_string(),
+                // Two holidays on one day
+                vec![holiday("2026-01-01", "New Year's Day"), holiday("2026-05-01", "Labour Day"), holiday("2026-05-01", "Spring Day")],
+            ),
+        ]
+    }
+
+    // The dates with the countries where they are a holiday
+    fn overlap(countries: &[(String, Vec<Holiday>)], at_least: usize) -> Vec<(String, Vec<&str>)> {
+        common_dates(countries, at_least)
+            .into_iter()
+            .map(|(date, date_countries)| (date.to_string(), date_countries.iter().map(|(code, _)| *code).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn common_dates_are_holidays_in_every_country() {
+        let countries = countries();
+        assert_eq!(overlap(&countries, 3), [("2026-01-01".to_string(), vec!["DE", "NL", "TR"])]);
+    }
+
+    #[test]
+    fn at_least_allows_countries_without_the_holiday() {
+        let countries = countries();
+        assert_eq!(
+            overlap(&countries, 2),
+            [
+                ("2026-01-01".to_string(), vec!["DE", "NL", "TR"]),
+                ("2026-05-01".to_string(), vec!["DE", "TR"]),
+                ("2026-12-25".to_string(), vec!["DE", "NL"]),
+            ]
+        );
+        assert_eq!(overlap(&countries, 1).len(), 4);
+        assert!(overlap(&countries, 4).is_empty());
+    }
+
+    #[test]
+    fn holidays_on_the_same_day_count_once() {
+        let countries = countries();
+        let dates = common_dates(&countries, 2);
+        let (_, may_day) = dates.iter().find(|(date, _)| date.to_string() == "2026-05-01").unwrap();
+        assert_eq!(may_day.len(), 2);
+        assert_eq!(names(&may_day[1].1), "Labour Day / Spring Day");
+
+        let mut out = Vec::new();
+        write_common(&mut out, &countries, 2, CommandFormat::Text).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("2026-05-01 (2 of 3)  DE: Labour Day, TR: Labour Day / Spring Day"), "{}", text);
+    }
+}
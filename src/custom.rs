@@ -0,0 +1,78 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::fs;
+
+use crate::Holiday;
+
+pub const CUSTOM_TYPE: &str = "Custom"; // Type added to every holiday from the custom holidays file
+
+// A holiday from the custom holidays file, in the shape of the API plus the country it belongs to
+#[derive(Deserialize, Debug, Clone)]
+pub struct CustomHoliday {
+    #[serde(deserialize_with = "crate::deserialize_date")] // The error points at the line and column of the date
+    date: NaiveDate,
+    #[serde(rename = "localName", default)]
+    local_name: Option<String>,
+    name: String,
+    #[serde(default)]
+    counties: Option<Vec<String>>,
+    #[serde(default)]
+    global: Option<bool>,
+    #[serde(default)]
+    fixed: Option<bool>,
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default, alias = "countryCode")]
+    country: Option<String>, // Applies to every country when missing
+}
+
+// The error names the line and column of the problem, a file that is not valid is never skipped silently
+pub fn read_custom_holidays(path: &str) -> Result<Vec<CustomHoliday>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content).map_err(|err| err.to_string())
+}
+
+// Adds the custom holidays of `country_code` in the years from `first_year` to `last_year`. A custom holiday
+// with the date and name of a fetched one is merged into it instead of listed twice.
+pub fn merge_custom_holidays(
+    holidays: &mut Vec<Holiday>,
+    custom_holidays: &[CustomHoliday],
+    country_code: &str,
+    first_year: i32,
+    last_year: i32,
+) {
+    for custom in custom_holidays {
+        if custom.country.as_ref().is_some_and(|country| !country.eq_ignore_ascii_case(country_code)) {
+            continue;
+        }
+        let year = custom.date.year();
+        if year < first_year || year > last_year {
+            continue;
+        }
+
+        if let Some(holiday) = holidays
+            .iter_mut()
+            .find(|holiday| holiday.date == custom.date && holiday.name.to_lowercase() == custom.name.to_lowercase())
+        {
+            if !holiday.types.iter().any(|holiday_type| holiday_type == CUSTOM_TYPE) {
+                holiday.types.push(CUSTOM_TYPE.to_string());
+            }
+            continue;
+        }
+        let mut types = custom.types.clone();
+        if !types.iter().any(|holiday_type| holiday_type == CUSTOM_TYPE) {
+            types.push(CUSTOM_TYPE.to_string());
+        }
+        holidays.push(Holiday {
+            date: custom.date,
+            local_name: custom.local_name.clone(),
+            name: custom.name.clone(),
+            counties: custom.counties.clone(),
+            global: custom.global,
+            fixed: custom.fixed,
+            types,
+            observed: None,
+        });
+    }
+    holidays.sort_by_key(|holiday| holiday.date);
+}
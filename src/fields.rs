@@ -0,0 +1,72 @@
+// Fields that can be selected with --fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Date,
+    Name,
+    LocalName,
+    Counties,
+    Types,
+    Weekday,
+    DaysUntil,
+    Country,
+    Fixed,
+}
+
+const FIELDS: [(&str, Field); 9] = [
+    ("date", Field::Date),
+    ("name", Field::Name),
+    ("local_name", Field::LocalName),
+    ("counties", Field::Counties),
+    ("types", Field::Types),
+    ("weekday", Field::Weekday),
+    ("days_until", Field::DaysUntil),
+    ("country", Field::Country),
+    ("fixed", Field::Fixed),
+];
+
+impl Field {
+    // Key in the JSON output and header in the CSV and TSV output
+    pub fn name(self) -> &'static str {
+        FIELDS
+            .iter()
+            .find(|(_, field)| *field == self)
+            .map(|(name, _)| *name)
+            .expect("every field has a name")
+    }
+
+    // Label in the text output and header in the table output
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Date => "Date",
+            Field::Name => "Name",
+            Field::LocalName => "Local name",
+            Field::Counties => "Counties",
+            Field::Types => "Types",
+            Field::Weekday => "Weekday",
+            Field::DaysUntil => "Days until",
+            Field::Country => "Country",
+            Field::Fixed => "Fixed",
+        }
+    }
+}
+
+// Parses a comma separated list such as "date,name", the fields are printed in the given order
+pub fn parse_fields(list: &str) -> Result<Vec<Field>, String> {
+    let mut fields = Vec::new();
+    for name in list.split(',').map(str::trim) {
+        let field = FIELDS
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+            .ok_or_else(|| format!("unknown field '{}'. Valid fields are: {}", name, valid_fields()))?;
+        if fields.contains(&field) {
+            return Err(format!("field '{}' is listed more than once", name));
+        }
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+fn valid_fields() -> String {
+    FIELDS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use crate::CommandFormat;
+
+// Response of the CountryInfo endpoint. The API only fills in the borders of the requested country,
+// deeper levels are requested one country at a time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CountryInfo {
+    pub common_name: String,
+    pub official_name: String,
+    pub country_code: String,
+    pub region: String,
+    #[serde(default)]
+    pub borders: Option<Vec<CountryInfo>>,
+}
+
+pub fn write_info(out: &mut dyn Write, info: &CountryInfo, format: CommandFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(info)?)?,
+        CommandFormat::Text => {
+            writeln!(out, "{} ({})", info.common_name, info.official_name)?;
+            writeln!(out, "Code: {}", info.country_code)?;
+            writeln!(out, "Region: {}", info.region)?;
+            // No borders at all with --borders-depth 0
+            match info.borders.as_deref() {
+                Some([]) => writeln!(out, "Borders: none")?,
+                Some(borders) => {
+                    writeln!(out, "Borders:")?;
+                    write_borders(out, borders, 1)?;
+                }
+                None => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+// Every level of neighbours is indented by two more spaces
+fn write_borders(out: &mut dyn Write, borders: &[CountryInfo], level: usize) -> std::io::Result<()> {
+    for border in borders {
+        writeln!(out, "{}{}  {}", "  ".repeat(level), border.country_code, border.common_name)?;
+        if let Some(borders) = &border.borders {
+            write_borders(out, borders, level + 1)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use crate::CommandFormat;
+
+// Response of the LongWeekend endpoint, the JSON output keeps the field names of the API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LongWeekend {
+    pub start_date: String,
+    pub end_date: String,
+    pub day_count: u32,
+    pub need_bridge_day: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] // Only sent by newer versions of the API
+    pub bridge_days: Vec<String>,
+}
+
+impl LongWeekend {
+    pub fn end(&self) -> Option<NaiveDate> {
+        parse_date(&self.end_date)
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+// "Fri 2026-04-03", dates the API sends in another format are printed as they are
+fn day(date: &str) -> String {
+    parse_date(date).map(|date| date.format("%a %Y-%m-%d").to_string()).unwrap_or_else(|| date.to_string())
+}
+
+pub fn write_long_weekends(
+    out: &mut dyn Write,
+    long_weekends: &[LongWeekend],
+    country_code: &str,
+    format: CommandFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(long_weekends)?)?,
+        CommandFormat::Text => {
+            if long_weekends.is_empty() {
+                writeln!(out, "No long weekends in {}.", country_code)?;
+            }
+            for long_weekend in long_weekends {
+                let bridge_day = match (long_weekend.need_bridge_day, long_weekend.bridge_days.as_slice()) {
+                    (false, _) => String::new(),
+                    (true, []) => "  needs a bridge day".to_string(),
+                    (true, days) => format!(
+                        "  needs a bridge day: {}",
+                        days.iter().map(|date| day(date)).collect::<Vec<_>>().join(", ")
+                    ),
+                };
+                writeln!(
+                    out,
+                    "{} – {}  {:>2} days{}",
+                    day(&long_weekend.start_date),
+                    day(&long_weekend.end_date),
+                    long_weekend.day_count,
+                    bridge_day
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,250 +1,3420 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
-use reqwest;
-use chrono::{NaiveDate, Local, Datelike};
-use std::fs;
-use serde_json;
+use chrono::{NaiveDate, Local, Datelike, Duration, Months, SecondsFormat, Utc, Weekday};
+use regex::Regex;
+use futures_util::stream::{self, StreamExt};
 use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+mod binary_cache;
+mod bridge;
+mod bundled;
+mod cache;
+mod compare;
+mod custom;
+mod fields;
+mod info;
+mod long_weekends;
+mod output;
+mod provider;
+mod report;
+mod template;
+#[cfg(test)]
+mod test_server;
+
+use fields::Field;
+use info::CountryInfo;
+use long_weekends::LongWeekend;
+use provider::{HolidayKind, Provider, ProviderOptions};
+use report::ReportTemplate;
+use template::LineTemplate;
+
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args { 
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Country Codes, several countries are listed one after another
+    #[arg(value_name = "COUNTRY", required_unless_present_any = ["template_help", "stdin"])]
+    countries: Vec<String>,
+
+    /// Read the country codes from stdin, one per line. Blank lines and lines starting with # are skipped
+    #[arg(long, conflicts_with = "countries")]
+    stdin: bool,
+
+    /// How many countries are requested from the API at the same time
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    concurrency: usize,
+
+    /// Stop at the first country that can't be fetched instead of listing the others
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Year to list holidays for (defaults to the current year)
+    #[arg(long)]
+    year: Option<i32>,
+
+    /// List every remaining holiday instead of only the next five
+    #[arg(long)]
+    all: bool,
+
+    /// Number of upcoming holidays to list
+    #[arg(long, default_value_t = 5, conflicts_with_all = ["all", "include_past"])]
+    limit: usize,
+
+    /// Only list the first N of the filtered and sorted holidays, instead of --limit
+    #[arg(long, value_name = "N", conflicts_with_all = ["limit", "last"])]
+    first: Option<usize>,
+
+    /// Only list the last N of the filtered and sorted holidays, keeping their order
+    #[arg(long, value_name = "N", conflicts_with_all = ["limit"])]
+    last: Option<usize>,
+
+    /// List holidays that have already occurred, most recent first
+    #[arg(long, conflicts_with = "include_past")]
+    past: bool,
+
+    /// List the whole year in order and mark the holidays that have already occurred
+    #[arg(long)]
+    include_past: bool,
+
+    /// Output format of the listed holidays (defaults to text on a terminal and tsv otherwise)
+    #[arg(long, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Export the listed holidays as an iCalendar file (stdout when no file is given)
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-", conflicts_with = "output")]
+    ics: Option<String>,
+
+    /// Write the output to this file instead of stdout ("-" for stdout)
+    #[arg(short = 'o', long, value_name = "PATH", conflicts_with = "ics")]
+    output_file: Option<String>,
+
+    /// Create missing parent directories of the output file
+    #[arg(long)]
+    mkdir: bool,
+
+    /// Maximum width of the counties column in table output, longer lists are truncated
+    #[arg(long, default_value_t = 30)]
+    counties_width: usize,
+
+    /// Template for each output line, e.g. "{date} {name} ({types})", '{{' and '}}' are literal braces
+    #[arg(long, conflicts_with_all = ["output", "ics"])]
+    format: Option<String>,
+
+    /// Comma separated fields to print in the text, table, CSV, TSV and JSON output, e.g. "date,name"
+    #[arg(long, value_name = "FIELDS", conflicts_with = "format")]
+    fields: Option<String>,
+
+    /// Render the holidays through a minijinja template file instead of an output format
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "ics", "format", "fields"])]
+    template: Option<String>,
+
+    /// List the variables available in --template files
+    #[arg(long)]
+    template_help: bool,
+
+    /// When to color the text and table output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Start the TSV output with a header row
+    #[arg(long)]
+    header: bool,
+
+    /// Wrap the HTML output in a complete HTML page
+    #[arg(long)]
+    full_page: bool,
+
+    /// Indent the XML output
+    #[arg(long)]
+    pretty: bool,
+
+    /// Print a bare list in the JSON and YAML output instead of an object with the country, source and timestamps
+    #[arg(long)]
+    no_envelope: bool,
+
+    /// Set the :CATEGORY: property of the org output
+    #[arg(long, value_name = "CATEGORY")]
+    org_category: Option<String>,
+
+    /// Start every line of the text and table output with the flag of the country
+    #[arg(long)]
+    flags: bool,
+
+    /// Print full weekday names instead of abbreviations
+    #[arg(long)]
+    long_weekdays: bool,
+
+    /// Group the text and table output under a header per month
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+
+    /// Also show months without holidays when grouping by month
+    #[arg(long, requires = "group_by")]
+    show_empty_months: bool,
+
+    /// Only print the holidays, without informational messages
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Also print details such as the dates of a --range
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// How long cached holidays are used, e.g. "7d", "12h" or "0" to always request them (defaults to 7d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, global = true)]
+    cache_ttl: Option<Duration>,
+
+    /// Always request the holidays, without reading them from or writing them to the cache
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Request the holidays again and replace them in the cache
+    #[arg(long, global = true, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Wait for expired cached holidays to be requested again instead of listing them first
+    #[arg(long, global = true)]
+    wait_fresh: bool,
+
+    /// Fail without a connection instead of showing expired or bundled holidays, e.g. in CI
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Most holiday entries kept in the cache, the least recently used ones are removed first (defaults to 1000)
+    #[arg(long, value_name = "N", global = true)]
+    max_cache_entries: Option<usize>,
+
+    /// Write the cache compressed with gzip, to holidays_cache.json.gz
+    #[arg(long, global = true)]
+    compress_cache: bool,
+
+    /// Format the cache is written in, binary is read faster (defaults to json)
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    cache_format: Option<CacheFormat>,
+
+    /// Directory of the cache file, instead of the platform cache directory
+    #[arg(long, value_name = "DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Cache file to use, e.g. on a mounted volume (defaults to $GET_HOLIDAY_CACHE, then the cache directory)
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "cache_dir")]
+    cache_file: Option<PathBuf>,
+
+    /// Create the missing directories of --cache-file or $GET_HOLIDAY_CACHE instead of stopping with an error
+    #[arg(long, global = true)]
+    create_dirs: bool,
+
+    /// Send requests for years outside the range the API has data for
+    #[arg(long, global = true)]
+    allow_any_year: bool,
+
+    /// Base URL of the holiday API of the --provider or the first configured provider, for a mirror or a local copy
+    #[arg(long, value_name = "URL", global = true)]
+    api_url: Option<String>,
+
+    /// API the holidays are requested from, without falling back to the providers of the config file
+    #[arg(long, value_enum, global = true)]
+    provider: Option<Provider>,
+
+    /// Request public or school holidays, only OpenHolidays has school holidays
+    #[arg(long, value_enum, global = true, default_value_t = HolidayKind::Public)]
+    kind: HolidayKind,
+
+    /// Request the holidays of this subdivision, e.g. DE-BY, with OpenHolidays and Calendarific
+    #[arg(long, value_name = "CODE", global = true)]
+    subdivision: Option<String>,
+
+    /// Language of the holiday names in the human readable output
+    #[arg(long, value_enum, default_value_t = Language::English)]
+    lang: Language,
+
+    /// Only print the next holiday with a countdown (exits with 2 if none remain this year)
+    #[arg(long, conflicts_with_all = ["past", "include_past", "calendar"])]
+    next: bool,
+
+    /// Show which days to take off to turn holidays into long weekends
+    #[arg(long)]
+    bridge_days: bool,
+
+    /// Order of the listed holidays, applied before the limit. Type order is Public, Bank, School, then the rest
+    #[arg(long, value_enum, default_value_t = SortKey::Date)]
+    sort: SortKey,
+
+    /// Reverse the order of the listed holidays (after the limit is applied)
+    #[arg(long)]
+    reverse: bool,
+
+    /// List the holidays from this date on (YYYY-MM-DD), until the end of its year unless --to is given
+    #[arg(long, value_name = "DATE", value_parser = parse_date, conflicts_with_all = ["year", "month", "past", "include_past", "next"])]
+    from: Option<NaiveDate>,
+
+    /// List the holidays up to this date (YYYY-MM-DD), from the start of its year unless --from is given
+    #[arg(long, value_name = "DATE", value_parser = parse_date, conflicts_with_all = ["year", "month", "past", "include_past", "next"])]
+    to: Option<NaiveDate>,
+
+    /// Read the holidays from this JSON file in the shape of the API instead of the cache and the API
+    #[arg(long, value_name = "FILE", conflicts_with = "stdin")]
+    holidays_file: Option<String>,
+
+    /// JSON file with extra holidays to list, e.g. company holidays (defaults to custom_holidays.json if it exists)
+    #[arg(long, value_name = "FILE")]
+    custom_holidays: Option<String>,
+
+    /// Only list national holidays and those of this county, e.g. "DE-BY" (can be repeated)
+    #[arg(long = "county", value_name = "COUNTY")]
+    counties: Vec<String>,
+
+    /// Only list holidays of this type, e.g. "public" (can be repeated)
+    #[arg(long = "type", value_name = "TYPE")]
+    types: Vec<String>,
+
+    /// Leave out holidays that only have this type, e.g. "school" (can be repeated)
+    #[arg(long = "exclude-type", value_name = "TYPE")]
+    excluded_types: Vec<String>,
+
+    /// Print only the number of matching holidays
+    #[arg(long, conflicts_with_all = ["limit", "next", "calendar", "on", "prompt", "template", "format", "fields"])]
+    count: bool,
+
+    /// Exit with status 2 when --count is 0
+    #[arg(long, requires = "count")]
+    fail_if_none: bool,
+
+    /// Only list holidays whose English or local name contains this text, ignoring case (can be repeated)
+    #[arg(long, value_name = "TEXT")]
+    search: Vec<String>,
+
+    /// Only list holidays whose English or local name matches this regular expression
+    #[arg(long, value_name = "REGEX")]
+    name_regex: Option<String>,
+
+    /// List the holidays whose name does not match --name-regex instead
+    #[arg(long, requires = "name_regex")]
+    invert_match: bool,
+
+    /// Leave out the holiday with this English or local name, ignoring case. "DE:Reformation Day" only applies to DE (can be repeated)
+    #[arg(long, value_name = "NAME")]
+    ignore: Vec<String>,
+
+    /// List the ignored holidays too, marked as ignored
+    #[arg(long)]
+    show_ignored: bool,
+
+    /// Only list holidays that fall on a working day
+    #[arg(long, group = "day_filter")]
+    workdays_only: bool,
+
+    /// Only list holidays that fall on a weekend
+    #[arg(long, group = "day_filter")]
+    weekends_only: bool,
+
+    /// Working days for --workdays-only and --weekends-only, e.g. "sun-thu" where the weekend is Friday and Saturday
+    #[arg(long, value_name = "DAYS", value_parser = parse_workweek, default_value = "mon-fri", requires = "day_filter")]
+    workweek: Workweek,
+
+    /// Only list holidays that are on the same date every year
+    #[arg(long, conflicts_with = "movable_only")]
+    fixed_only: bool,
+
+    /// Only list holidays whose date changes from year to year, such as Easter
+    #[arg(long)]
+    movable_only: bool,
+
+    /// Only list holidays that apply to the whole country
+    #[arg(long, conflicts_with = "counties")]
+    national_only: bool,
+
+    /// List the holidays of a period relative to today, weeks start on Monday
+    #[arg(long, value_enum, conflicts_with_all = ["from", "to", "year", "month", "past", "include_past", "next"])]
+    range: Option<NamedRange>,
+
+    /// List the holidays in the next N days, not counting today
+    #[arg(long, value_name = "DAYS", conflicts_with_all = ["from", "to", "range", "year", "month", "past", "include_past", "next"])]
+    within: Option<u32>,
+
+    /// Check whether a date (YYYY-MM-DD) is a holiday, exits with status 1 when it is not, 2 on errors
+    #[arg(long, value_name = "DATE", value_parser = parse_date, conflicts_with_all = ["year", "from", "to", "month", "past", "include_past", "next", "calendar"])]
+    on: Option<NaiveDate>,
+
+    /// Print only the days until the next holiday, from the cache and without network access
+    #[arg(long, conflicts_with_all = ["next", "calendar", "output", "ics", "format", "template"])]
+    prompt: bool,
+
+    /// Text printed by --prompt, {days}, {name} and {date} are replaced
+    #[arg(long, value_name = "FORMAT", default_value = "🎉 {days}d", requires = "prompt")]
+    prompt_format: String,
+
+    /// Print a month calendar with the holidays marked instead of a list
+    #[arg(long, conflicts_with_all = ["output", "ics", "format"])]
+    calendar: bool,
+
+    /// Only list the holidays of this month, as a number or name such as "12", "dec" or "december".
+    /// Also the month shown by --calendar (defaults to the current month)
+    #[arg(long, value_parser = parse_month, conflicts_with_all = ["past", "next"])]
+    month: Option<u32>,
+
+    /// First day of the week in the calendar
+    #[arg(long, value_enum, default_value_t = WeekStart::Monday)]
+    week_start: WeekStart,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Print the JSON Schema (draft 2020-12) of the JSON output
+    Schema {
+        /// Format the schema is printed in
+        #[arg(long, value_enum, default_value_t = SchemaFormat::Json)]
+        format: SchemaFormat,
+    },
+    /// Compare the holidays of two countries by date
+    Diff {
+        /// Country code of the first country
+        left: String,
+
+        /// Country code of the second country
+        right: String,
+
+        #[command(flatten)]
+        dates: CompareDates,
+
+        /// Format the comparison is printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// List the dates that are holidays in all of the given countries
+    Common {
+        /// Country codes of the countries
+        #[arg(required = true, num_args = 2..)]
+        countries: Vec<String>,
+
+        /// Also list dates that are holidays in only K of the countries
+        #[arg(long, value_name = "K")]
+        at_least: Option<usize>,
+
+        #[command(flatten)]
+        dates: CompareDates,
+
+        /// Format the dates are printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// List the holidays of the next days in every country
+    Worldwide {
+        /// Format the holidays are printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// Print the name, region and neighbours of a country
+    Info {
+        /// Country code
+        country: String,
+
+        /// Levels of neighbours to list, 2 also lists the neighbours of the neighbours
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        borders_depth: usize,
+
+        /// Format the information is printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// List the long weekends of a year, including the ones that need a bridge day
+    Longweekends {
+        /// Country code
+        country: String,
+
+        /// Year to list the long weekends for (defaults to the current year)
+        #[arg(long)]
+        year: Option<i32>,
+
+        /// Only list long weekends of at least this many days
+        #[arg(long, value_name = "DAYS")]
+        min_days: Option<u32>,
+
+        /// Only list long weekends that have not ended yet
+        #[arg(long)]
+        upcoming_only: bool,
+
+        /// Format the long weekends are printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// Exit with status 0 when today is a public holiday, 1 when it is not and 2 on errors
+    IsToday {
+        /// Country code
+        country: String,
+
+        /// Also count the holidays of this county, e.g. DE-BY
+        #[arg(long)]
+        county: Option<String>,
+
+        /// UTC offset in hours that decides which day today is, e.g. -5 (defaults to the local time zone)
+        #[arg(long, value_name = "HOURS", allow_negative_numbers = true, value_parser = clap::value_parser!(i64).range(-12..=14))]
+        offset: Option<i64>,
+    },
+    /// Check whether the holiday API can be reached and print its version
+    ApiStatus,
+    /// Manage the cache file
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Print the holidays of every country for this and next year, for data/bundled_holidays.json
+    #[command(hide = true)]
+    BundleData,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove the cached holidays, of one country when a country code is given
+    Clear {
+        /// Country code
+        country: Option<String>,
+
+        /// Only remove the entries of this year
+        #[arg(long)]
+        year: Option<i32>,
+    },
+    /// List the cached holidays with their age
+    List {
+        /// Format the list is printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// Write the cached holidays to a file, to import them on another machine
+    Export {
+        /// File to write the export to (stdout when no file is given)
+        #[arg(short = 'o', long, value_name = "PATH")]
+        output_file: Option<String>,
+
+        /// Create missing parent directories of the output file
+        #[arg(long)]
+        mkdir: bool,
+    },
+    /// Add the holidays of an export to the cache
+    Import {
+        /// File written by cache export
+        file: String,
+    },
+    /// Time reading a generated cache as JSON and in the binary format, and looking up its entries
+    #[command(hide = true)]
+    Benchmark {
+        /// Countries in the generated cache, at most one for every code of two letters
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u16).range(1..=676))]
+        countries: u16,
+
+        /// Years of every country
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=50))]
+        years: i32,
+
+        /// How often each format is parsed, the median is printed
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..=1000))]
+        rounds: u32,
+    },
+    /// Summarize the cache, e.g. to check that it is warm
+    Stats {
+        /// Format the summary is printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+    /// Cache the holidays of every available country, e.g. before going offline
+    Prewarm {
+        /// Comma separated years to cache, e.g. "2025,2026" (defaults to the current year)
+        #[arg(long, value_name = "YEARS", value_delimiter = ',')]
+        years: Vec<i32>,
+
+        /// How many countries are requested from the API at the same time
+        #[arg(long, value_name = "N", default_value_t = 4)]
+        concurrency: usize,
+
+        /// Milliseconds between the start of two requests, to go easy on the API
+        #[arg(long, value_name = "MS", default_value_t = 250)]
+        delay: u64,
+
+        /// Also request the entries that are still fresh
+        #[arg(long)]
+        force: bool,
+
+        /// Format the summary is printed in
+        #[arg(long, value_enum, default_value_t = CommandFormat::Text)]
+        format: CommandFormat,
+    },
+}
+
+// The dates looked at by the subcommands that compare countries
+#[derive(clap::Args, Debug)]
+struct CompareDates {
+    /// Year to compare (defaults to the current year)
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    year: Option<i32>,
+
+    /// Only compare holidays on or after this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date)]
+    from: Option<NaiveDate>,
+
+    /// Only compare holidays on or before this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date)]
+    to: Option<NaiveDate>,
+}
+
+// Output format of the subcommands
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandFormat {
+    Text,
+    Json,
+}
+
+// How the cache is stored. JSON stays readable and can be edited by hand, binary is read faster.
+#[derive(clap::ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum CacheFormat {
+    Json,
+    Binary,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SchemaFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Local,   // Name in the country's own language
+    English,
+    Both,    // "localName (name)"
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum NamedRange {
+    ThisWeek,
+    NextWeek,
+    ThisMonth,
+    NextMonth,
+    ThisQuarter, // Calendar quarters, January to March and so on
+    NextQuarter,
+    ThisYear,
+}
+
+impl NamedRange {
+    // First and last day of the range, both inclusive
+    fn bounds(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let first_of_month = today.with_day(1).expect("first day of month is valid");
+        let first_of_quarter = first_of_month.with_month((today.month() - 1) / 3 * 3 + 1).expect("quarter month is valid");
+        // From `first` to the day before `months` after it
+        let months_from = |first: NaiveDate, months: u32| {
+            (first, first + Months::new(months) - Duration::days(1))
+        };
+        match self {
+            NamedRange::ThisWeek => (monday, monday + Duration::days(6)),
+            NamedRange::NextWeek => (monday + Duration::days(7), monday + Duration::days(13)),
+            NamedRange::ThisMonth => months_from(first_of_month, 1),
+            NamedRange::NextMonth => months_from(first_of_month + Months::new(1), 1),
+            NamedRange::ThisQuarter => months_from(first_of_quarter, 3),
+            NamedRange::NextQuarter => months_from(first_of_quarter + Months::new(3), 3),
+            NamedRange::ThisYear => months_from(first_of_month.with_month(1).expect("January is valid"), 12),
+        }
+    }
+}
+
+// The days after today up to `days` later, both inclusive. The last day can be in the next year.
+fn within_bounds(today: NaiveDate, days: u32) -> (NaiveDate, NaiveDate) {
+    (today + Duration::days(1), today + Duration::days(days as i64))
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Date,
+    Name, // Case and accent insensitive
+    Type, // Public, Bank, School, then other types
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupBy {
+    Month,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum WeekStart {
+    Monday,
+    Sunday,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    Auto,   // Colors when stdout is a terminal and NO_COLOR is not set
+    Always,
+    Never,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text, // One line per holiday
+    Json, // A JSON object with the holidays and where they came from
+    Csv,  // CSV with a header row
+    Ics,  // iCalendar with one all-day event per holiday
+    Yaml, // The JSON object in YAML
+    Table, // Column aligned table with a header row
+    Tsv,  // Tab separated fields without quoting
+    Html, // An HTML table fragment
+    Xml,  // An XML document with one element per holiday
+    Ndjson, // One JSON object per line
+    Toml, // An array of tables per country
+    Org,  // Org-mode headings with a SCHEDULED timestamp
+    Remind, // REM lines for remind(1)
+    Taskwarrior, // A JSON array for `task import`
+    Waybar, // A status bar module showing the next holiday
+    ScriptFilter, // Alfred and Raycast script filter items
+}
+
+// A --first or --last slice of the listed holidays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slice {
+    First(usize),
+    Last(usize),
+}
+
+// Which part of the year is listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Upcoming,  // Holidays from today on
+    Past,      // Holidays before today, most recent first
+    WholeYear, // Every holiday of the year
+    Month(u32), // Every holiday of one month, 1 to 12
+    Range(NaiveDate, NaiveDate), // Every holiday between two dates, both inclusive
+}
+
+// The working days of a week, from `first` to `last` and wrapping around Sunday
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Workweek {
+    first: Weekday,
+    last: Weekday,
+}
+
+impl Workweek {
+    fn contains(&self, weekday: Weekday) -> bool {
+        let days_after_first = (weekday.num_days_from_monday() + 7 - self.first.num_days_from_monday()) % 7;
+        let length = (self.last.num_days_from_monday() + 7 - self.first.num_days_from_monday()) % 7;
+        days_after_first <= length
+    }
+}
+
+// The days of the week a holiday has to fall on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DayFilter {
+    Workdays(Workweek),
+    Weekends(Workweek), // Every day outside the work week
+}
+
+fn parse_workweek(value: &str) -> Result<Workweek, String> {
+    let invalid = || format!("'{}' is not a work week, use two weekdays such as 'mon-fri' or 'sun-thu'", value);
+    let (first, last) = value.split_once('-').ok_or_else(invalid)?;
+    Ok(Workweek {
+        first: first.trim().parse().map_err(|_| invalid())?,
+        last: last.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+// A number with the unit s, m, h, d or w, such as "7d" or "90m". A bare number is in seconds, so "0" works
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{}' is not a duration, use a number with a unit such as '7d', '12h' or '30m'", value);
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    match unit.trim() {
+        "" | "s" => Ok(Duration::seconds(number)),
+        "m" => Ok(Duration::minutes(number)),
+        "h" => Ok(Duration::hours(number)),
+        "d" => Ok(Duration::days(number)),
+        "w" => Ok(Duration::weeks(number)),
+        _ => Err(invalid()),
+    }
+}
+
+// A holiday name given with --ignore or in the config file, optionally limited to one country
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnoreRule {
+    country_code: Option<String>, // Upper case, the rule applies to every country without it
+    name: String,                 // Lower case
+}
+
+impl IgnoreRule {
+    fn parse(value: &str) -> IgnoreRule {
+        match value.split_once(':') {
+            Some((country_code, name)) if country_code.len() == 2 && country_code.chars().all(|c| c.is_ascii_alphabetic()) => {
+                IgnoreRule { country_code: Some(country_code.to_uppercase()), name: name.trim().to_lowercase() }
+            }
+            _ => IgnoreRule { country_code: None, name: value.trim().to_lowercase() },
+        }
+    }
+
+    fn matches(&self, holiday: &Holiday, country_code: &str) -> bool {
+        self.country_code.as_ref().is_none_or(|code| code == country_code)
+            && std::iter::once(&holiday.name).chain(&holiday.local_name).any(|name| name.to_lowercase() == self.name)
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("'{}' is not a date, use the format YYYY-MM-DD", value))
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+// Accepts a month number or an English month name, full or abbreviated to three letters
+fn parse_month(value: &str) -> Result<u32, String> {
+    let value = value.trim().to_lowercase();
+    if let Ok(month) = value.parse::<u32>() {
+        return if (1..=12).contains(&month) {
+            Ok(month)
+        } else {
+            Err(format!("'{}' is not a month, use a number from 1 to 12", month))
+        };
+    }
+    MONTH_NAMES
+        .iter()
+        .position(|name| *name == value || (value.len() == 3 && name.starts_with(&value)))
+        .map(|index| index as u32 + 1)
+        .ok_or_else(|| format!("'{}' is not a month, use a number from 1 to 12 or a name such as 'dec' or 'december'", value))
+}
+
+// Settings that control which holidays are listed and how
+#[derive(Debug, Clone)]
+struct ListConfig {
+    country_code: String,
+    year: i32,
+    today: NaiveDate,
+    period: Period,
+    mark_past: bool, // Holidays before today are marked in the output
+    all: bool,       // No limit on the number of listed holidays
+    limit: usize,
+    slice: Option<Slice>, // Replaces the limit when given
+    output: OutputFormat,
+    output_file: Option<String>, // Output is written to stdout when no file is given
+    create_dirs: bool,           // Missing parent directories of the output file are created
+    counties_width: usize,
+    line_template: Option<LineTemplate>, // Replaces the default text line when given
+    fields: Option<Vec<Field>>,          // Replaces the default fields of the output when given
+    report_template: Option<ReportTemplate>, // Replaces the output format when given
+    color: bool,                         // ANSI colors in the text and table output
+    header: bool,                        // Header row in the TSV output
+    full_page: bool,                     // Complete page instead of a fragment in the HTML output
+    pretty: bool,                        // Indented XML output
+    org_category: Option<String>,        // :CATEGORY: property in the org output
+    long_weekdays: bool,                 // "Saturday" instead of "Sat"
+    group_by: Option<GroupBy>,
+    show_empty_months: bool,
+    language: Language,
+    bridge_days: bool, // Bridge day hints in the text output
+    flags: bool,       // Country flag in front of every text line and table row
+    sort: SortKey,
+    reverse: bool,
+    count: bool, // Only the number of holidays is printed
+    fail_if_none: bool,
+    counties: Vec<String>, // Upper case county codes given with --county
+    types: Vec<String>,    // Lower case types given with --type
+    excluded_types: Vec<String>, // Lower case types given with --exclude-type
+    national_only: bool,
+    fixed: Option<bool>, // Set with --fixed-only or --movable-only
+    day_filter: Option<DayFilter>, // Set with --workdays-only or --weekends-only
+    search: Vec<String>, // Lower case search terms
+    name_regex: Option<Regex>,
+    invert_match: bool,
+    ignored: Vec<IgnoreRule>, // From --ignore and the config file
+    show_ignored: bool,       // Ignored holidays are listed with a marker instead of left out
+    envelope: bool, // JSON and YAML output wrapped in an object with the details below
+    source: DataSource,
+    provider: Option<String>, // The provider the holidays came from, e.g. "openholidays/school/DE-BY"
+    fetched_at: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema, Debug, Clone)]
+struct Holiday { 
+    #[serde(deserialize_with = "deserialize_date")]
+    date: NaiveDate,
+    #[serde(rename = "localName", default)] // Missing in caches written by older versions
+    local_name: Option<String>,
+    name: String,
+    counties: Option<Vec<String>>, // Counties information is optional
+    #[serde(default, skip_serializing_if = "Option::is_none")] // Missing in caches written by older versions
+    global: Option<bool>, // True when the holiday applies to the whole country
+    #[serde(default)] // Missing in caches written by older versions, where it is unknown
+    fixed: Option<bool>, // True when the holiday is on the same date every year
+    types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] // Only holidayapi.com sends it
+    observed: Option<NaiveDate>, // The day off when it differs from the date, e.g. the Monday after a Sunday holiday
+}
+
+// Like chrono's own, but the error names the date, e.g. "invalid date '2026-02-30', expected YYYY-MM-DD"
+pub(crate) fn deserialize_date<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|_| serde::de::Error::custom(format!("invalid date '{}', expected YYYY-MM-DD", date)))
+}
+
+impl Holiday {
+    // Falls back to the English name when the local name is unknown
+    fn display_name(&self, language: Language) -> String {
+        match (language, &self.local_name) {
+            (Language::Local, Some(local_name)) => local_name.clone(),
+            (Language::Both, Some(local_name)) if *local_name != self.name => format!("{} ({})", local_name, self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    // Older cache entries have no global flag, there a holiday without counties applies to the whole country
+    fn is_national(&self) -> bool {
+        self.global.unwrap_or(self.counties.is_none())
+    }
+
+    fn counties_text(&self) -> String {
+        match &self.counties {
+            Some(counties) if !self.is_national() => counties.join(", "),
+            _ => "National".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedData { 
+    provider: String, // ProviderOptions::cache_key of the provider the holidays came from
+    country_code: String,
+    year: i32,
+    #[serde(deserialize_with = "deserialize_date")]
+    date: NaiveDate, // Day the entry was written, the age of entries without fetched_at
+    #[serde(default)] // Missing in caches written by older versions
+    fetched_at: Option<String>, // When the holidays were requested from the API
+    #[serde(flatten)] // Missing when the API sent none
+    validators: Validators,
+    #[serde(default, skip_serializing_if = "Option::is_none")] // Missing until the entry is used from the cache
+    last_accessed: Option<String>,
+    holidays: Vec<Holiday>,
+}
+
+impl CachedData {
+    // The cache and the bundled data are looked up the same way
+    fn is_for(&self, provider: &str, country_code: &str, year: i32) -> bool {
+        self.provider == provider && self.country_code == country_code && self.year == year
+    }
+
+    fn key(&self) -> cache::CacheKey {
+        (self.provider.clone(), self.country_code.clone(), self.year)
+    }
+
+    // The holidays of a year rarely change, so an entry is used until it is older than the cache TTL
+    fn is_fresh(&self) -> bool {
+        self.age().is_some_and(|age| age < cache_ttl())
+    }
+
+    // Entries that were never used from the cache count from when they were fetched
+    fn unused_for(&self) -> Option<Duration> {
+        match &self.last_accessed {
+            Some(last_accessed) => chrono::DateTime::parse_from_rfc3339(last_accessed)
+                .ok()
+                .map(|last_accessed| Utc::now() - last_accessed.with_timezone(&Utc)),
+            None => self.age(),
+        }
+    }
+
+    // Entries without fetched_at only know the day they were written
+    fn age(&self) -> Option<Duration> {
+        match &self.fetched_at {
+            Some(fetched_at) => chrono::DateTime::parse_from_rfc3339(fetched_at)
+                .ok()
+                .map(|fetched_at| Utc::now() - fetched_at.with_timezone(&Utc)),
+            None => Some(Local::now().date_naive() - self.date),
+        }
+    }
+}
+
+// Where the holidays of a year came from
+#[derive(Serialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum DataSource {
+    Cache,
+    Api,
+    Bundled, // The snapshot of the bundled-data feature
+    File, // --holidays-file
+}
+
+#[derive(Debug, Clone)]
+struct HolidayData {
+    holidays: Vec<Holiday>,
+    source: DataSource,
+    provider: Option<String>, // ProviderOptions::cache_key, none for --holidays-file
+    fetched_at: Option<String>,
+    validators: Validators,
+}
+
+// Sent back with the next request of the same holidays, so the API can answer that they have not changed
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct FullCache {
+    version: u64, // cache::CACHE_VERSION, older caches are migrated when they are read
+    #[serde(deserialize_with = "deserialize_date")]
+    date: NaiveDate, // Day of the last write
+    data: cache::CacheEntries,
+    #[serde(default, skip_serializing_if = "Option::is_none")] // Missing in caches written by older versions
+    worldwide: Option<WorldwideCache>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] // Missing in caches written by older versions
+    countries: Option<CountriesCache>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")] // Missing in caches written by older versions
+    country_info: BTreeMap<String, CountryInfo>, // By country code, never expires
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] // Missing in caches written by older versions
+    long_weekends: Vec<CachedLongWeekends>,
+}
+
+// The long weekends depend on the holidays, so they expire after the same time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedLongWeekends {
+    country_code: String,
+    year: i32,
+    #[serde(default)] // Missing in caches written by older versions, these entries are fetched again
+    fetched_at: Option<String>,
+    long_weekends: Vec<LongWeekend>,
+}
+
+// A country supported by the holiday API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AvailableCountry {
+    #[serde(rename = "countryCode")]
+    country_code: String,
+    name: String, // Empty when read from COUNTRY_CODES_FILE
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CountriesCache {
+    fetched_at: String,
+    countries: Vec<AvailableCountry>,
+}
+
+impl FullCache {
+    fn new(today: NaiveDate) -> FullCache {
+        FullCache {
+            version: cache::CACHE_VERSION,
+            date: today,
+            data: cache::CacheEntries::default(),
+            worldwide: None,
+            countries: None,
+            country_info: BTreeMap::new(),
+            long_weekends: Vec::new(),
+        }
+    }
+}
+
+// The holidays of the next days in every country, they are outdated quickly so they have their own entry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WorldwideCache {
+    fetched_at: String,
+    holidays: Vec<WorldwideHoliday>,
+}
+
+// The worldwide endpoint also says which country a holiday belongs to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WorldwideHoliday {
+    #[serde(rename = "countryCode")]
+    country_code: String,
+    #[serde(flatten)]
+    holiday: Holiday,
+}
+
+const DEFAULT_CACHE_FILE: &str = "holidays_cache.json" ; // cache file in the cache directory where data will be saved
+const DEFAULT_COMPRESSED_CACHE_FILE: &str = "holidays_cache.json.gz"; // cache file with --compress-cache
+const DEFAULT_BINARY_CACHE_FILE: &str = "holidays_cache.bin"; // cache file with --cache-format binary
+const DEFAULT_COMPRESSED_BINARY_CACHE_FILE: &str = "holidays_cache.bin.gz"; // cache file with both
+const CACHE_FILE_ENV: &str = "GET_HOLIDAY_CACHE"; // Environment variable with the path of the cache file, --cache-file wins
+const CONFIG_FILE: &str = "get_holiday.toml"; // Optional settings in the working directory
+const CUSTOM_HOLIDAYS_FILE: &str = "custom_holidays.json"; // Optional extra holidays in the working directory
+const COUNTRY_CODES_FILE: &str = "country_codes.txt"; // Country codes used when the API can't be reached
+const CACHE_TTL_DAYS: i64 = 7; // How long the holidays of a year are used from the cache without --cache-ttl
+const MAX_CACHE_ENTRIES_DEFAULT: usize = 1000; // Every country for several years, without --max-cache-entries
+const FETCH_RETRIES: u32 = 2; // How often a request is repeated when the API is down
+const PREWARM_RETRY_SECS: u64 = 30; // Wait before a rate limited prewarm request is repeated, unless the API says otherwise
+const PREWARM_MAX_RETRY_SECS: u64 = 120; // Longest wait for a rate limited prewarm request, a longer Retry-After counts as a failure
+const COUNTRIES_CACHE_DAYS: i64 = 30; // How long the list of available countries is used from the cache
+const DEFAULT_API_URL: &str = "https://date.nager.at"; // Base URL of the holiday API
+const WORLDWIDE_CACHE_HOURS: i64 = 1; // How long the worldwide holidays are used from the cache
+const MIN_YEAR: i32 = 1975; // Earliest year served by the holiday API
+const MAX_YEARS_AHEAD: i32 = 10; // How far into the future a year may be requested
+const HOLIDAY_TYPES: [&str; 7] = ["Public", "Bank", "School", "Authorities", "Optional", "Observance", custom::CUSTOM_TYPE]; // Types known to the holiday API
+const EXIT_NO_HOLIDAYS: i32 = 2; // Exit code when nothing is left to show, so scripts can tell it apart from errors
+const EXIT_NO_MATCHES: i32 = 3; // Exit code when no holiday matches --search or --name-regex
+const EXIT_PARTIAL_FAILURE: i32 = 4; // Exit code when some of several countries could not be fetched
+const EXIT_NOT_A_HOLIDAY: i32 = 1; // Exit code of --on and is-today when the day is not a holiday, the answer "no" like grep
+const EXIT_ANSWER_ERROR: i32 = 2; // Exit code of errors with --on and is-today, so they can't be taken for the answer "no"
+
+// Exit code of errors, EXIT_ANSWER_ERROR when the exit code is the answer
+static ERROR_EXIT_CODE: AtomicI32 = AtomicI32::new(1);
+// Informational messages and warnings are not printed with --quiet
+static QUIET: AtomicBool = AtomicBool::new(false);
+// Details that are only printed with --verbose
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+// Years are not checked with --allow-any-year
+static ALLOW_ANY_YEAR: AtomicBool = AtomicBool::new(false);
+// The holidays are neither read from nor written to the cache with --no-cache
+static NO_CACHE: AtomicBool = AtomicBool::new(false);
+// The holidays are requested again and replace the cached ones with --refresh
+static REFRESH: AtomicBool = AtomicBool::new(false);
+// The cache is written with gzip with --compress-cache or compress_cache in the config file
+static COMPRESS_CACHE: AtomicBool = AtomicBool::new(false);
+// Set once from --cache-format or cache_format in the config file
+static CACHE_FORMAT: OnceLock<CacheFormat> = OnceLock::new();
+
+fn cache_format() -> CacheFormat {
+    CACHE_FORMAT.get().copied().unwrap_or(CacheFormat::Json)
+}
+// Without a connection the run fails with --strict, instead of using expired or bundled holidays
+static STRICT: AtomicBool = AtomicBool::new(false);
+// Expired cached holidays are listed right away when holidays are listed, unless --wait-fresh is given
+static SERVE_STALE: AtomicBool = AtomicBool::new(false);
+// The expired entries that were used, with their age. They are requested again after the output.
+static STALE_ENTRIES: std::sync::Mutex<Vec<(String, i32, Duration)>> = std::sync::Mutex::new(Vec::new());
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("Error: {:?}", err);
+        exit_with_error();
+    }
+}
+
+fn output_format(args: &Args, stdout_is_terminal: bool) -> OutputFormat {
+    match (&args.ics, args.output) {
+        (Some(_), _) => OutputFormat::Ics,
+        (None, Some(output)) => output,
+        // Pipes get stable tab separated lines, unless options that only exist for the text output are used
+        (None, None) => {
+            let text_requested = args.format.is_some()
+                || args.group_by.is_some()
+                || args.bridge_days
+                || args.color == ColorChoice::Always;
+            if text_requested || (args.output_file.is_none() && stdout_is_terminal) {
+                OutputFormat::Text
+            } else {
+                OutputFormat::Tsv
+            }
+        }
+    }
+}
+
+fn exit_with_error() -> ! {
+    std::process::exit(ERROR_EXIT_CODE.load(Ordering::Relaxed))
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.on.is_some() || matches!(args.command, Some(Command::IsToday { .. })) {
+        ERROR_EXIT_CODE.store(EXIT_ANSWER_ERROR, Ordering::Relaxed);
+    }
+    let output = output_format(&args, std::io::stdout().is_terminal());
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    VERBOSE.store(args.verbose, Ordering::Relaxed);
+    ALLOW_ANY_YEAR.store(args.allow_any_year, Ordering::Relaxed);
+    NO_CACHE.store(args.no_cache, Ordering::Relaxed);
+    REFRESH.store(args.refresh, Ordering::Relaxed);
+    STRICT.store(args.strict, Ordering::Relaxed);
+    let config_file = read_config_file().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid {}: {}", CONFIG_FILE, err);
+        exit_with_error();
+    });
+    // The flag wins over the config file
+    let config_cache_ttl = config_file.cache_ttl.as_deref().map(parse_duration).transpose().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid cache_ttl in {}: {}", CONFIG_FILE, err);
+        exit_with_error();
+    });
+    if let Some(cache_ttl) = args.cache_ttl.or(config_cache_ttl) {
+        CACHE_TTL.set(cache_ttl).expect("the cache TTL is only set here");
+    }
+    if let Some(max_cache_entries) = args.max_cache_entries.or(config_file.max_cache_entries) {
+        MAX_CACHE_ENTRIES.set(max_cache_entries).expect("the cache size is only set here");
+    }
+    COMPRESS_CACHE.store(args.compress_cache || config_file.compress_cache, Ordering::Relaxed);
+    if let Some(cache_format) = args.cache_format.or(config_file.cache_format) {
+        CACHE_FORMAT.set(cache_format).expect("the cache format is only set here");
+    }
+    set_cache_location(&args);
+    let providers = provider_list(&args, &config_file);
+    // The country list, info and long weekends always come from Nager
+    let nager_url = providers
+        .iter()
+        .find(|options| options.provider == Provider::Nager)
+        .map(|options| options.base_url.clone())
+        .unwrap_or(DEFAULT_API_URL.to_string());
+    API_URL.set(nager_url).expect("the API URL is only set here");
+    PROVIDERS.set(providers).expect("the providers are only set here");
+
+    match &args.command {
+        Some(Command::Schema { format }) => {
+            output::write_schema(&mut std::io::stdout().lock(), *format)?;
+            return Ok(());
+        }
+        Some(Command::Diff { left, right, dates, format }) => return run_diff(left, right, dates, *format).await,
+        Some(Command::Common { countries, at_least, dates, format }) => {
+            return run_common(countries, *at_least, dates, *format).await
+        }
+        Some(Command::Worldwide { format }) => {
+            let holidays = get_worldwide_holidays().await?;
+            output::write_worldwide(&mut std::io::stdout().lock(), &holidays, *format)?;
+            return Ok(());
+        }
+        Some(Command::Info { country, borders_depth, format }) => {
+            let today = Local::now().date_naive();
+            let country_code = validate_country_code(country, &get_available_countries(today).await?);
+            let info = get_country_info_tree(&country_code, *borders_depth, today).await?;
+            info::write_info(&mut std::io::stdout().lock(), &info, *format)?;
+            return Ok(());
+        }
+        Some(Command::Longweekends { country, year, min_days, upcoming_only, format }) => {
+            let today = Local::now().date_naive();
+            let country_code = validate_country_code(country, &get_available_countries(today).await?);
+            let year = year.unwrap_or(today.year());
+            validate_year(year, today.year());
+            let mut long_weekends = get_long_weekends(&country_code, year, today).await?;
+            long_weekends.retain(|long_weekend| {
+                min_days.is_none_or(|min_days| long_weekend.day_count >= min_days)
+                    && (!upcoming_only || long_weekend.end().is_some_and(|end| end >= today))
+            });
+            long_weekends.sort_by(|a, b| a.start_date.cmp(&b.start_date));
+            long_weekends::write_long_weekends(&mut std::io::stdout().lock(), &long_weekends, &country_code, *format)?;
+            return Ok(());
+        }
+        Some(Command::IsToday { country, county, offset }) => return run_is_today(country, county.as_deref(), *offset).await,
+        Some(Command::ApiStatus) => return run_api_status().await,
+        Some(Command::Cache { action: CacheCommand::Clear { country, year } }) => {
+            return cache::clear_cache(country.as_deref(), *year, Local::now().date_naive())
+        }
+        Some(Command::Cache { action: CacheCommand::List { format } }) => {
+            return cache::write_cache_list(&mut std::io::stdout().lock(), *format)
+        }
+        Some(Command::Cache { action: CacheCommand::Benchmark { countries, years, rounds } }) => {
+            return cache::run_benchmark(&mut std::io::stdout().lock(), usize::from(*countries), *years, *rounds as usize)
+        }
+        Some(Command::Cache { action: CacheCommand::Stats { format } }) => {
+            return cache::write_cache_stats(&mut std::io::stdout().lock(), *format)
+        }
+        Some(Command::Cache { action: CacheCommand::Export { output_file, mkdir } }) => {
+            return cache::export_cache(output_file.as_deref(), *mkdir)
+        }
+        Some(Command::Cache { action: CacheCommand::Import { file } }) => {
+            return cache::import_cache(file, Local::now().date_naive())
+        }
+        Some(Command::Cache { action: CacheCommand::Prewarm { years, concurrency, delay, force, format } }) => {
+            return run_cache_prewarm(years, *concurrency, *delay, *force, *format).await
+        }
+        Some(Command::BundleData) => return run_bundle_data().await,
+        None => {}
+    }
+
+    if args.template_help {
+        print!("{}", report::template_help());
+        return Ok(());
+    }
+
+    if args.prompt {
+        let [country] = args.countries.as_slice() else {
+            eprintln!("Error: '--prompt' can only be used with a single country.");
+            exit_with_error();
+        };
+        print_prompt(&country.to_uppercase(), args.year, &args.prompt_format, args.lang);
+        return Ok(());
+    }
+
+    // Nothing is requested with a holidays file, so the country can't be checked either
+    if let Some(holidays_file) = &args.holidays_file {
+        if args.countries.len() > 1 {
+            eprintln!("Error: '--holidays-file' can only be used with a single country.");
+            exit_with_error();
+        }
+        let holidays = read_holidays_file(holidays_file).unwrap_or_else(|err| {
+            eprintln!("Error: Invalid holidays file '{}': {}", holidays_file, err);
+            exit_with_error();
+        });
+        HOLIDAYS_FILE.set(holidays).expect("the holidays file is only read here");
+    }
+    let available_countries = if args.holidays_file.is_some() {
+        Vec::new()
+    } else {
+        get_available_countries(Local::now().date_naive()).await?
+    };
+
+    let country_codes = if args.holidays_file.is_some() {
+        args.countries.iter().map(|country| country.trim().to_uppercase()).collect()
+    } else if args.stdin {
+        read_stdin_country_codes(&available_countries)
+    } else {
+        let mut country_codes: Vec<String> = Vec::new();
+        for country in &args.countries {
+            let country_code = validate_country_code(country, &available_countries);
+            if !country_codes.contains(&country_code) {
+                country_codes.push(country_code);
+            }
+        }
+        country_codes
+    };
+
+    // These print a single answer or write a single file
+    if country_codes.len() > 1 {
+        let single_country_option = [
+            (args.on.is_some(), "--on"),
+            (args.next, "--next"),
+            (args.calendar, "--calendar"),
+            (output == OutputFormat::Waybar, "--output waybar"),
+            (args.output_file.is_some() || args.ics.is_some(), "--output-file"),
+        ]
+        .into_iter()
+        .find_map(|(used, option)| used.then_some(option));
+        if let Some(option) = single_country_option {
+            eprintln!("Error: '{}' can only be used with a single country.", option);
+            exit_with_error();
+        }
+    }
+
+
+    let today = Local::now().date_naive(); 
+    let current_year = Local::now().year();
+    // A missing end of the range is the end of the other date's year
+    let range = match (args.from, args.to) {
+        (None, None) => args
+            .range
+            .map(|named_range| named_range.bounds(today))
+            .or(args.within.map(|days| within_bounds(today, days))),
+        (Some(from), to) => Some((from, to.unwrap_or(NaiveDate::from_ymd_opt(from.year(), 12, 31).expect("Dec 31 is valid")))),
+        (None, Some(to)) => Some((NaiveDate::from_ymd_opt(to.year(), 1, 1).expect("Jan 1 is valid"), to)),
+    };
+    if let Some((from, to)) = range {
+        print_verbose(&format!("Listing holidays from {} to {}.", from, to));
+        if to < from {
+            eprintln!("Error: '--to' ({}) must not be before '--from' ({}).", to, from);
+            exit_with_error();
+        }
+    }
+    let year = range
+        .map(|(from, _)| from.year())
+        .or(args.on.map(|date| date.year()))
+        .or(args.year)
+        .unwrap_or(current_year);
+    let last_year = range.map(|(_, to)| to.year()).unwrap_or(year);
+
+    if args.holidays_file.is_none() {
+        for year in [year, last_year] {
+            validate_year(year, current_year);
+        }
+    }
+
+    if args.within == Some(0) {
+        eprintln!("Error: '--within' must be at least 1 day.");
+        exit_with_error();
+    }
+
+    if args.first == Some(0) || args.last == Some(0) {
+        eprintln!("Error: '--first' and '--last' must be at least 1.");
+        exit_with_error();
+    }
+
+    if args.concurrency == 0 {
+        eprintln!("Error: '--concurrency' must be at least 1.");
+        exit_with_error();
+    }
+
+    if args.limit == 0 {
+        eprintln!("Error: '--limit' must be at least 1. Use '--all' to list every holiday.");
+        exit_with_error();
+    }
+
+    let line_template = args.format.as_deref().map(LineTemplate::parse).transpose().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid --format template: {}", err);
+        exit_with_error();
+    });
+
+    let fields = args.fields.as_deref().map(fields::parse_fields).transpose().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid --fields: {}", err);
+        exit_with_error();
+    });
+
+    let name_regex = args.name_regex.as_deref().map(Regex::new).transpose().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid --name-regex: {}", err);
+        exit_with_error();
+    });
+
+    let report_template = args.template.as_deref().map(ReportTemplate::load).transpose().unwrap_or_else(|err| {
+        eprintln!("Error: Invalid --template: {}", err);
+        exit_with_error();
+    });
+
+    if args.holidays_file.is_none() {
+        create_cache_if_missing(today)?;
+    }
+
+    let fail_fast = args.fail_fast || country_codes.len() == 1;
+    // --strict never shows expired holidays, so it waits as well
+    SERVE_STALE.store(!args.wait_fresh && !STRICT.load(Ordering::Relaxed), Ordering::Relaxed);
+    let (mut country_data, failures) =
+        get_countries_holidays(&country_codes, year, last_year, today, args.concurrency, fail_fast).await?;
+    if country_data.is_empty() {
+        print_failures(&failures, country_codes.len());
+        exit_with_error();
+    }
+
+    // Only a file given with --custom-holidays has to exist
+    let custom_holidays_file = args.custom_holidays.as_deref().unwrap_or(CUSTOM_HOLIDAYS_FILE);
+    if args.custom_holidays.is_some() || Path::new(custom_holidays_file).exists() {
+        let custom_holidays = custom::read_custom_holidays(custom_holidays_file).unwrap_or_else(|err| {
+            eprintln!("Error: Invalid custom holidays file '{}': {}", custom_holidays_file, err);
+            exit_with_error();
+        });
+        for (country_code, data) in &mut country_data {
+            custom::merge_custom_holidays(&mut data.holidays, &custom_holidays, country_code, year, last_year);
+        }
+    }
+
+    // An unknown county would silently list only the national holidays, so it is most likely a typo.
+    // County codes start with the country code, so with several countries it has to appear in one of them.
+    let counties: Vec<String> = args.counties.iter().map(|county| county.trim().to_uppercase()).collect();
+    let known_counties: BTreeSet<String> = country_data
+        .iter()
+        .flat_map(|(_, data)| &data.holidays)
+        .filter_map(|holiday| holiday.counties.as_ref())
+        .flatten()
+        .map(|county| county.to_uppercase())
+        .collect();
+    for county in &counties {
+        if !known_counties.contains(county) {
+            eprintln!(
+                "Error: The county '{}' does not appear in the holidays of {}. Counties with their own holidays are: {:?}",
+                county,
+                country_codes.join(", "),
+                known_counties
+            );
+            exit_with_error();
+        }
+    }
+
+    // The API may add new types, so an unknown type is only a warning
+    let types: Vec<String> = args.types.iter().map(|holiday_type| holiday_type.trim().to_lowercase()).collect();
+    let excluded_types: Vec<String> =
+        args.excluded_types.iter().map(|holiday_type| holiday_type.trim().to_lowercase()).collect();
+    if let Some(holiday_type) = types.iter().find(|holiday_type| excluded_types.contains(holiday_type)) {
+        eprintln!("Error: The type '{}' can't be given with both '--type' and '--exclude-type'.", holiday_type);
+        exit_with_error();
+    }
+    for holiday_type in types.iter().chain(&excluded_types) {
+        if !HOLIDAY_TYPES.iter().any(|known_type| known_type.to_lowercase() == *holiday_type) {
+            print_info(&format!(
+                "Warning: '{}' is not a known holiday type. Known types are: {}",
+                holiday_type,
+                HOLIDAY_TYPES.join(", ")
+            ));
+        }
+    }
+
+    if (args.fixed_only || args.movable_only)
+        && country_data.iter().flat_map(|(_, data)| &data.holidays).any(|holiday| holiday.fixed.is_none())
+    {
+        print_info("Warning: Some cached holidays were saved without the fixed flag and are left out. Delete the cache file to fetch them again.");
+    }
+
+    // The single country modes, only one country can be given for these. They answer whether there is a holiday.
+    if let [(country_code, data)] = country_data.as_slice() {
+        let answer: Option<Result<bool, Box<dyn std::error::Error>>> = if let Some(date) = args.on {
+            Some(Ok(print_holidays_on(&data.holidays, date, country_code, args.lang)))
+        } else if args.next {
+            Some(
+                with_next_year_if_needed(&data.holidays, country_code, year, today)
+                    .await
+                    .map(|holidays| print_next_holiday(&holidays, today, country_code, args.lang)),
+            )
+        } else if output == OutputFormat::Waybar {
+            Some(with_next_year_if_needed(&data.holidays, country_code, year, today).await.and_then(|holidays| {
+                output::write_waybar(&mut std::io::stdout().lock(), &holidays, today, args.lang)?;
+                Ok(true)
+            }))
+        } else if args.calendar {
+            let month = args.month.unwrap_or(today.month());
+            Some(
+                output::write_calendar(&mut std::io::stdout().lock(), &data.holidays, year, month, args.week_start, args.lang)
+                    .map(|()| true),
+            )
+        } else {
+            None
+        };
+        if let Some(answer) = answer {
+            // These are the modes that get polled, so the expired holidays they showed are requested again here too
+            revalidate_stale_entries(today).await;
+            if !answer? {
+                std::process::exit(if args.on.is_some() { EXIT_NOT_A_HOLIDAY } else { EXIT_NO_HOLIDAYS });
+            }
+            return Ok(());
+        }
+    }
+
+    let name_filtered = !args.search.is_empty() || name_regex.is_some();
+    let period = if let Some((from, to)) = range {
+        Period::Range(from, to)
+    } else if let Some(month) = args.month {
+        // Past days of the month are listed as well
+        Period::Month(month)
+    } else if args.past {
+        Period::Past
+    } else if args.include_past || year != current_year || name_filtered {
+        // Only the current year is limited to upcoming holidays, otherwise a past year would print nothing.
+        // A search looks at the whole year.
+        Period::WholeYear
+    } else {
+        Period::Upcoming
+    };
+
+    // "-" stands for stdout
+    let output_file = args.output_file.or(args.ics).filter(|path| path != "-");
+    let color = match args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        // See https://no-color.org
+        ColorChoice::Auto => {
+            output_file.is_none()
+                && std::io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        }
+    };
+
+    let config = ListConfig {
+        country_code: String::new(),
+        year,
+        today,
+        period,
+        mark_past: args.include_past || args.month.is_some() || range.is_some() || name_filtered,
+        all: args.all || args.include_past || args.month.is_some() || range.is_some() || name_filtered || args.count,
+        limit: args.limit,
+        slice: args.first.map(Slice::First).or(args.last.map(Slice::Last)),
+        output,
+        output_file,
+        create_dirs: args.mkdir,
+        counties_width: args.counties_width,
+        line_template,
+        fields,
+        report_template,
+        color,
+        header: args.header,
+        full_page: args.full_page,
+        pretty: args.pretty,
+        org_category: args.org_category,
+        long_weekdays: args.long_weekdays,
+        group_by: args.group_by,
+        show_empty_months: args.show_empty_months,
+        language: args.lang,
+        bridge_days: args.bridge_days,
+        flags: args.flags,
+        sort: args.sort,
+        reverse: args.reverse,
+        count: args.count,
+        fail_if_none: args.fail_if_none,
+        counties,
+        types,
+        excluded_types,
+        national_only: args.national_only,
+        fixed: if args.fixed_only { Some(true) } else if args.movable_only { Some(false) } else { None },
+        day_filter: if args.workdays_only {
+            Some(DayFilter::Workdays(args.workweek))
+        } else if args.weekends_only {
+            Some(DayFilter::Weekends(args.workweek))
+        } else {
+            None
+        },
+        search: args.search.iter().map(|term| term.to_lowercase()).collect(),
+        name_regex,
+        invert_match: args.invert_match,
+        ignored: config_file.ignore.iter().chain(&args.ignore).map(|value| IgnoreRule::parse(value)).collect(),
+        show_ignored: args.show_ignored,
+        envelope: !args.no_envelope,
+        source: DataSource::Cache,
+        provider: None,
+        fetched_at: None,
+    };
+
+    let several_countries = country_codes.len() > 1;
+    // Machine readable formats get a single document, so every country is selected before it is written
+    let together = several_countries
+        && !config.count
+        && config.report_template.is_none()
+        && output::lists_countries_together(config.output);
+    let listed: Result<usize, Box<dyn std::error::Error>> = async {
+        let mut holidays_listed = 0;
+        let mut countries = Vec::new();
+        for (index, (country_code, data)) in country_data.into_iter().enumerate() {
+            // Each country gets a heading in the output meant for people
+            if several_countries && !config.count && matches!(config.output, OutputFormat::Text | OutputFormat::Table) {
+                println!("{}{}", if index > 0 { "\n" } else { "" }, country_code);
+            }
+            let country_config = ListConfig {
+                country_code,
+                source: data.source,
+                provider: data.provider,
+                fetched_at: data.fetched_at,
+                ..config.clone()
+            };
+            if together {
+                let selected_holidays = select_holidays(&data.holidays, &country_config).await?;
+                holidays_listed += selected_holidays.len();
+                countries.push((country_config, selected_holidays));
+            } else {
+                holidays_listed += list_holidays(&data.holidays, &country_config, several_countries).await?;
+            }
+        }
+        if together {
+            output::write_countries_holidays(&mut std::io::stdout().lock(), &countries)?;
+            for (country_config, selected_holidays) in &countries {
+                print_listed_count(selected_holidays.len(), country_config);
+            }
+        }
+        Ok(holidays_listed)
+    }
+    .await;
+    // Also when the output failed, the expired holidays were still shown
+    revalidate_stale_entries(today).await;
+    let holidays_listed = listed?;
+
+    // The countries that were fetched are listed, then the run fails as a whole
+    if !failures.is_empty() {
+        print_failures(&failures, country_codes.len());
+        std::process::exit(EXIT_PARTIAL_FAILURE);
+    }
+
+    if holidays_listed == 0 {
+        if config.count && config.fail_if_none {
+            std::process::exit(EXIT_NO_HOLIDAYS);
+        }
+        if !config.count && (!config.search.is_empty() || config.name_regex.is_some()) {
+            print_info("No holidays match the name filter.");
+            std::process::exit(EXIT_NO_MATCHES);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_available(country_code: &str, available_countries: &[AvailableCountry]) -> bool {
+    available_countries.iter().any(|country| country.country_code == country_code)
+}
+
+fn validate_country_code(country: &str, available_countries: &[AvailableCountry]) -> String {
+    let country_code = country.trim().to_uppercase();
+    if !is_available(&country_code, available_countries) {
+        // The names are unknown when the countries come from COUNTRY_CODES_FILE
+        let valid_countries: Vec<String> = available_countries
+            .iter()
+            .map(|country| match country.name.as_str() {
+                "" => country.country_code.clone(),
+                name => format!("{} ({})", country.country_code, name),
+            })
+            .collect();
+        eprintln!(
+            "Error: '{}' is not a valid country code. Valid countries are: {}",
+            country_code,
+            valid_countries.join(", ")
+        );
+        exit_with_error();
+    }
+    country_code
+}
+
+// The API has no data before MIN_YEAR, so such a request would only end in a 404
+fn validate_year(year: i32, current_year: i32) {
+    if let Err(err) = check_year(year, current_year, ALLOW_ANY_YEAR.load(Ordering::Relaxed)) {
+        eprintln!("Error: {}", err);
+        exit_with_error();
+    }
+}
+
+fn check_year(year: i32, current_year: i32, allow_any_year: bool) -> Result<(), String> {
+    if allow_any_year || (MIN_YEAR..=current_year + MAX_YEARS_AHEAD).contains(&year) {
+        return Ok(());
+    }
+    Err(format!(
+        "'{}' is not a valid year. The holiday API has data from {} to {}, use --allow-any-year to send the request anyway.",
+        year,
+        MIN_YEAR,
+        current_year + MAX_YEARS_AHEAD
+    ))
+}
+
+// Fetches the holidays of the countries between the dates given to a comparing subcommand,
+// by default the whole current year
+async fn get_compared_holidays(
+    countries: &[&String],
+    dates: &CompareDates,
+) -> Result<Vec<(String, Vec<Holiday>)>, Box<dyn std::error::Error>> {
+    let today = Local::now().date_naive();
+    let available_countries = get_available_countries(today).await?;
+    let country_codes: Vec<String> =
+        countries.iter().map(|country| validate_country_code(country, &available_countries)).collect();
+
+    let year = dates.from.map(|from| from.year()).or(dates.year).unwrap_or(today.year());
+    let from = dates.from.unwrap_or(NaiveDate::from_ymd_opt(year, 1, 1).expect("Jan 1 is valid"));
+    let to = dates.to.unwrap_or(NaiveDate::from_ymd_opt(year, 12, 31).expect("Dec 31 is valid"));
+    if to < from {
+        eprintln!("Error: '--to' ({}) must not be before '--from' ({}).", to, from);
+        exit_with_error();
+    }
+    for year in [from.year(), to.year()] {
+        validate_year(year, today.year());
+    }
+
+    create_cache_if_missing(today)?;
+    let (country_data, _) = get_countries_holidays(&country_codes, from.year(), to.year(), today, country_codes.len(), true).await?;
+    Ok(country_data
+        .into_iter()
+        .map(|(country_code, data)| {
+            let holidays = data
+                .holidays
+                .into_iter()
+                .filter(|holiday| from <= holiday.date && holiday.date <= to)
+                .collect();
+            (country_code, holidays)
+        })
+        .collect())
+}
+
+async fn run_diff(left: &String, right: &String, dates: &CompareDates, format: CommandFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let country_data = get_compared_holidays(&[left, right], dates).await?;
+    let [(left_code, left_holidays), (right_code, right_holidays)] = country_data.as_slice() else {
+        unreachable!("both countries are fetched or the program exits");
+    };
+    compare::write_diff(
+        &mut std::io::stdout().lock(),
+        (left_code, left_holidays),
+        (right_code, right_holidays),
+        format,
+    )
+}
+
+// Response of the Version endpoint
+#[derive(Deserialize, Debug)]
+struct ApiVersion {
+    name: String,
+    version: String,
+}
+
+// Meant for bug reports, so it tells apart a missing connection, an unhealthy API and a wrong --api-url
+async fn run_api_status() -> Result<(), Box<dyn std::error::Error>> {
+    let url = format!("{}/api/v3/Version", api_url());
+    println!("API URL: {}", api_url());
+    let started = Instant::now();
+    let response = http_client().get(&url).send().await;
+    let latency = started.elapsed().as_millis();
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            println!("Status: {}", status);
+            println!("Latency: {} ms", latency);
+            match response.json::<ApiVersion>().await {
+                Ok(version) => println!("Version: {} {}", version.name, version.version),
+                Err(_) => println!("Version: unknown"),
+            }
+            if !status.is_success() {
+                exit_with_error();
+            }
+        }
+        Err(err) => {
+            println!("Status: unreachable ({})", FetchError::from(err));
+            exit_with_error();
+        }
+    }
+    Ok(())
+}
+
+// Writes the snapshot for the bundled-data feature, e.g. `get_holiday bundle-data > data/bundled_holidays.json`
+async fn run_bundle_data() -> Result<(), Box<dyn std::error::Error>> {
+    if provider_options().provider != Provider::Nager {
+        eprintln!("Error: The bundled data always comes from Nager, '--provider' can't be used with bundle-data.");
+        exit_with_error();
+    }
+    let today = Local::now().date_naive();
+    let country_codes: Vec<String> =
+        get_available_countries(today).await?.into_iter().map(|country| country.country_code).collect();
+    create_cache_if_missing(today)?;
+    let (country_data, _) = get_countries_holidays(&country_codes, today.year(), today.year() + 1, today, 4, true).await?;
+
+    let mut data = Vec::new();
+    for (country_code, country) in country_data {
+        for year in [today.year(), today.year() + 1] {
+            data.push(CachedData {
+                provider: provider::default_cache_key(),
+                country_code: country_code.clone(),
+                year,
+                date: today,
+                fetched_at: country.fetched_at.clone(),
+                validators: Validators::default(),
+                last_accessed: None,
+                holidays: country
+                    .holidays
+                    .iter()
+                    .filter(|holiday| holiday.date.year() == year)
+                    .cloned()
+                    .collect(),
+            });
+        }
+    }
+    let bundled = bundled::BundledData { generated_at: Some(today.to_string()), data };
+    println!("{}", serde_json::to_string(&bundled)?);
+    Ok(())
+}
+
+// What cache prewarm did, a failed entry doesn't stop the others
+#[derive(Serialize, Debug)]
+struct PrewarmSummary {
+    countries: usize,
+    years: Vec<i32>,
+    fetched: usize,
+    fresh: usize, // Skipped because they were in the cache already
+    empty: usize, // The API has no holidays for them, they are asked for again next time
+    failed: Vec<PrewarmFailure>,
+    seconds: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct PrewarmFailure {
+    country: String,
+    year: i32,
+    reason: String,
+}
+
+// Every available country for every year. The requests start at least `delay` milliseconds apart with up to
+// `concurrency` at the same time, and the cache is written once at the end like for several countries.
+async fn run_cache_prewarm(
+    years: &[i32],
+    concurrency: usize,
+    delay: u64,
+    force: bool,
+    format: CommandFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if NO_CACHE.load(Ordering::Relaxed) {
+        eprintln!("Error: '--no-cache' can't be used with cache prewarm.");
+        exit_with_error();
+    }
+    if concurrency == 0 {
+        eprintln!("Error: '--concurrency' must be at least 1.");
+        exit_with_error();
+    }
+    let today = Local::now().date_naive();
+    let years: Vec<i32> = if years.is_empty() {
+        vec![today.year()]
+    } else {
+        years.iter().copied().collect::<BTreeSet<_>>().into_iter().collect()
+    };
+    for year in &years {
+        validate_year(*year, today.year());
+    }
+    let started = Instant::now();
+    let country_codes: Vec<String> =
+        get_available_countries(today).await?.into_iter().map(|country| country.country_code).collect();
+    create_cache_if_missing(today)?;
+    // Fresh entries are only requested with --force, and the offline fallbacks would count as cached
+    if force {
+        REFRESH.store(true, Ordering::Relaxed);
+    }
+    STRICT.store(true, Ordering::Relaxed);
+
+    let full_cache = cache::load_cache().ok().flatten();
+    let providers = providers();
+    let (fresh, requests): (Vec<_>, Vec<_>) = country_codes
+        .iter()
+        .flat_map(|country_code| years.iter().map(move |year| (country_code.as_str(), *year)))
+        .partition(|(country_code, year)| {
+            !force
+                && full_cache.as_ref().is_some_and(|full_cache| {
+                    providers.iter().any(|options| {
+                        full_cache
+                            .data
+                            .get(&(options.cache_key(), country_code.to_string(), *year))
+                            .is_some_and(CachedData::is_fresh)
+                    })
+                })
+        });
+
+    // Every request takes the next start time, so the API sees at most one new request per `delay`
+    let next_start = std::sync::Mutex::new(tokio::time::Instant::now());
+    let next_start = &next_start;
+    let delay = std::time::Duration::from_millis(delay);
+    let mut results = stream::iter(requests.iter().copied())
+        .map(|(country_code, year)| async move {
+            let start = {
+                let mut next_start = next_start.lock().unwrap_or_else(|err| err.into_inner());
+                let start = cmp::max(*next_start, tokio::time::Instant::now());
+                *next_start = start + delay;
+                start
+            };
+            tokio::time::sleep_until(start).await;
+            (country_code, year, prewarm_holidays(country_code, year, today).await)
+        })
+        .buffer_unordered(concurrency);
+
+    // The progress is only shown on a terminal, it would only clutter a log
+    let progress = !QUIET.load(Ordering::Relaxed) && std::io::stderr().is_terminal();
+    let mut summary =
+        PrewarmSummary { countries: country_codes.len(), years, fetched: 0, fresh: fresh.len(), empty: 0, failed: Vec::new(), seconds: 0 };
+    let mut new_entries = Vec::new();
+    let mut done = 0;
+    while let Some((country_code, year, result)) = results.next().await {
+        done += 1;
+        if progress {
+            eprint!("\r\x1b[KCaching {} of {}: {} {}", done, requests.len(), country_code, year);
+        }
+        match result {
+            Ok(data) if data.holidays.is_empty() => summary.empty += 1,
+            Ok(data) => {
+                summary.fetched += 1;
+                new_entries.push(CachedData {
+                    provider: data.provider.unwrap_or_else(|| provider_options().cache_key()),
+                    country_code: country_code.to_string(),
+                    year,
+                    date: today,
+                    fetched_at: data.fetched_at,
+                    validators: data.validators,
+                    last_accessed: None,
+                    holidays: data.holidays,
+                });
+            }
+            Err(err) => {
+                print_verbose(&format!("Could not get the holidays of {} {}: {}", country_code, year, err));
+                summary.failed.push(PrewarmFailure { country: country_code.to_string(), year, reason: err.reason() });
+            }
+        }
+    }
+    if progress {
+        eprint!("\r\x1b[K");
+    }
+    write_cache(today, new_entries)?;
+    summary.failed.sort_by(|a, b| (&a.country, a.year).cmp(&(&b.country, b.year)));
+    summary.seconds = started.elapsed().as_secs();
+
+    let mut out = std::io::stdout().lock();
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&summary)?)?,
+        CommandFormat::Text => {
+            let years: Vec<String> = summary.years.iter().map(|year| year.to_string()).collect();
+            writeln!(out, "Countries: {} for {} in {} seconds", summary.countries, years.join(", "), summary.seconds)?;
+            writeln!(out, "Fetched: {}", summary.fetched)?;
+            writeln!(out, "Already fresh: {}", summary.fresh)?;
+            writeln!(out, "Without holidays: {}", summary.empty)?;
+            let failed: Vec<String> =
+                summary.failed.iter().map(|failure| format!("{} {} ({})", failure.country, failure.year, failure.reason)).collect();
+            if failed.is_empty() {
+                writeln!(out, "Failed: 0")?;
+            } else {
+                writeln!(out, "Failed: {} ({})", failed.len(), failed.join(", "))?;
+            }
+        }
+    }
+    if !summary.failed.is_empty() {
+        out.flush()?;
+        std::process::exit(EXIT_PARTIAL_FAILURE);
+    }
+    Ok(())
+}
+
+// A rate limited request is repeated once after the wait the API asks for
+async fn prewarm_holidays(country_code: &str, year: i32, today: NaiveDate) -> Result<HolidayData, FetchError> {
+    match get_holidays_uncached(country_code, year, today).await {
+        Err(FetchError::RateLimited { retry_after }) if retry_after.unwrap_or(PREWARM_RETRY_SECS) <= PREWARM_MAX_RETRY_SECS => {
+            let seconds = retry_after.unwrap_or(PREWARM_RETRY_SECS);
+            print_verbose(&format!("The rate limit of the API was reached, requesting {} {} again in {} seconds.", country_code, year, seconds));
+            tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+            get_holidays_uncached(country_code, year, today).await
+        }
+        result => result,
+    }
+}
+
+// Like --on, the exit status is the answer: 0 for a holiday, EXIT_NOT_A_HOLIDAY when it is not one and
+// EXIT_ANSWER_ERROR for errors
+async fn run_is_today(country: &str, county: Option<&str>, offset: Option<i64>) -> Result<(), Box<dyn std::error::Error>> {
+    let local_today = Local::now().date_naive();
+    let today = offset.map(|offset| (Utc::now() + Duration::hours(offset)).date_naive()).unwrap_or(local_today);
+    let country_code = validate_country_code(country, &get_available_countries(local_today).await?);
+    let county = county.map(|county| county.trim().to_uppercase());
+
+    create_cache_if_missing(local_today)?;
+    let holidays = get_holidays(&country_code, today.year(), local_today).await?.holidays;
+    let todays_holidays: Vec<&Holiday> = holidays
+        .iter()
+        .filter(|holiday| holiday.date == today)
+        .filter(|holiday| holiday.types.iter().any(|holiday_type| holiday_type == "Public"))
+        .filter(|holiday| {
+            holiday.is_national()
+                || county.as_ref().is_some_and(|county| {
+                    holiday.counties.as_ref().is_some_and(|counties| counties.iter().any(|code| code.eq_ignore_ascii_case(county)))
+                })
+        })
+        .collect();
+
+    if todays_holidays.is_empty() {
+        print_verbose(&format!("{} is not a public holiday in {}.", today, county.as_deref().unwrap_or(&country_code)));
+        std::process::exit(EXIT_NOT_A_HOLIDAY);
+    }
+    for holiday in todays_holidays {
+        println!("{}", holiday.name);
+    }
+    Ok(())
+}
+
+async fn run_common(
+    countries: &[String],
+    at_least: Option<usize>,
+    dates: &CompareDates,
+    format: CommandFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The same country given twice would count twice
+    let mut unique_countries: Vec<&String> = Vec::new();
+    for country in countries {
+        if !unique_countries.iter().any(|unique| unique.eq_ignore_ascii_case(country)) {
+            unique_countries.push(country);
+        }
+    }
+    let at_least = at_least.unwrap_or(unique_countries.len());
+    if at_least == 0 || at_least > unique_countries.len() {
+        eprintln!("Error: '--at-least' must be between 1 and the number of countries ({}).", unique_countries.len());
+        exit_with_error();
+    }
+
+    let country_data = get_compared_holidays(&unique_countries, dates).await?;
+    compare::write_common(&mut std::io::stdout().lock(), &country_data, at_least, format)
+}
+
+// The holidays of every country for every year from `year` to `last_year`, because a range over a year
+// boundary needs all years it touches. Up to `concurrency` requests run at the same time, and the
+// cache is written once at the end so the requests don't overwrite each other's entries.
+async fn get_countries_holidays(
+    country_codes: &[String],
+    year: i32,
+    last_year: i32,
+    today: NaiveDate,
+    concurrency: usize,
+    fail_fast: bool,
+) -> Result<(Vec<(String, HolidayData)>, Vec<FetchFailure>), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let requests: Vec<(&str, i32)> = country_codes
+        .iter()
+        .flat_map(|country_code| (year..=last_year).map(move |year| (country_code.as_str(), year)))
+        .collect();
+    let mut results = stream::iter(requests.iter().copied())
+        .map(|(country_code, year)| async move { (country_code, year, get_holidays_uncached(country_code, year, today).await) })
+        .buffered(concurrency);
+
+    let mut new_entries = Vec::new();
+    let mut country_data: Vec<(String, HolidayData)> = Vec::new();
+    let mut failures: Vec<FetchFailure> = Vec::new();
+    while let Some((country_code, year, result)) = results.next().await {
+        // A country without one of its years is left out completely
+        if failures.iter().any(|failure| failure.country_code == country_code) {
+            continue;
+        }
+        let data = match result {
+            Ok(data) => data,
+            Err(err) if fail_fast => exit_with_fetch_error(&err),
+            Err(err) => {
+                print_verbose(&format!("Could not get the holidays of {} {}: {}", country_code, year, err));
+                country_data.retain(|(fetched_country_code, _)| fetched_country_code != country_code);
+                failures.push(FetchFailure { country_code: country_code.to_string(), reason: err.reason() });
+                continue;
+            }
+        };
+        // An empty answer is not cached, so the next run asks again
+        if data.source == DataSource::Api && !data.holidays.is_empty() {
+            new_entries.push(CachedData {
+                provider: data.provider.clone().unwrap_or_else(|| provider_options().cache_key()),
+                country_code: country_code.to_string(),
+                year,
+                date: today,
+                fetched_at: data.fetched_at.clone(),
+                validators: data.validators.clone(),
+                last_accessed: None,
+                holidays: data.holidays.clone(),
+            });
+        }
+        // The results are in the order of the requests, so the years of a country follow each other
+        match country_data.last_mut() {
+            Some((last_country_code, country)) if last_country_code == country_code => {
+                country.holidays.extend(data.holidays);
+                if data.source == DataSource::Api {
+                    country.source = DataSource::Api;
+                    country.provider = data.provider;
+                }
+            }
+            _ => country_data.push((country_code.to_string(), data)),
+        }
+    }
+    if !new_entries.is_empty() {
+        write_cache(today, new_entries)?;
+        print_verbose(&format!("Fetched the holidays of {} countries in {} ms.", country_codes.len(), started.elapsed().as_millis()));
+    }
+    Ok((country_data, failures))
+}
+
+// A country that could not be fetched in a run with several countries
+#[derive(Debug)]
+struct FetchFailure {
+    country_code: String,
+    reason: String,
+}
+
+// For example "2 of 5 countries failed: XK (404), FR (timeout)"
+fn print_failures(failures: &[FetchFailure], countries: usize) {
+    let failed_countries: Vec<String> =
+        failures.iter().map(|failure| format!("{} ({})", failure.country_code, failure.reason)).collect();
+    eprintln!("{} of {} countries failed: {}", failures.len(), countries, failed_countries.join(", "));
+}
+
+// One country code per line with line numbers in the errors. Blank lines and comments after # are skipped
+fn read_stdin_country_codes(available_countries: &[AvailableCountry]) -> Vec<String> {
+    let lines: Vec<String> = std::io::stdin().lines().collect::<Result<_, _>>().unwrap_or_else(|err| {
+        eprintln!("Error: Could not read the country codes from stdin: {}", err);
+        exit_with_error();
+    });
+    let parsed = parse_country_code_lines(&lines, available_countries);
+    for (line_number, country_code) in &parsed.invalid {
+        eprintln!("Error: line {}: '{}' is not a valid country code.", line_number, country_code);
+    }
+    if !parsed.invalid.is_empty() {
+        let invalid_codes: Vec<String> =
+            parsed.invalid.iter().map(|(line_number, country_code)| format!("{} (line {})", country_code, line_number)).collect();
+        eprintln!(
+            "Error: {} of {} country codes are not valid: {}",
+            invalid_codes.len(),
+            parsed.codes_given,
+            invalid_codes.join(", ")
+        );
+        exit_with_error();
+    }
+    if parsed.country_codes.is_empty() {
+        eprintln!("Error: No country codes were given on stdin.");
+        exit_with_error();
+    }
+    parsed.country_codes
+}
+
+#[derive(Debug, Default)]
+struct CountryCodeLines {
+    country_codes: Vec<String>,     // Upper case, each code once in the order of the lines
+    invalid: Vec<(usize, String)>, // Line number, starting at 1, and the code
+    codes_given: usize,            // Lines with a code, valid or not
+}
+
+// Blank lines and comments after # are skipped
+fn parse_country_code_lines(lines: &[String], available_countries: &[AvailableCountry]) -> CountryCodeLines {
+    let mut parsed = CountryCodeLines::default();
+    for (index, line) in lines.iter().enumerate() {
+        let country_code = line.split('#').next().unwrap_or_default().trim().to_uppercase();
+        if country_code.is_empty() {
+            continue;
+        }
+        parsed.codes_given += 1;
+        if !is_available(&country_code, available_countries) {
+            parsed.invalid.push((index + 1, country_code));
+        } else if !parsed.country_codes.contains(&country_code) {
+            parsed.country_codes.push(country_code);
+        }
+    }
+    parsed
+}
+
+async fn get_holidays(country_code: &str, year: i32, today: NaiveDate) -> Result<HolidayData, Box<dyn std::error::Error>> {
+    let data = get_holidays_uncached(country_code, year, today).await.unwrap_or_else(|err| exit_with_fetch_error(&err));
+    if data.source == DataSource::Api && !data.holidays.is_empty() {
+        write_cache(
+            today,
+            vec![CachedData {
+                provider: data.provider.clone().unwrap_or_else(|| provider_options().cache_key()),
+                country_code: country_code.to_string(),
+                year,
+                date: today,
+                fetched_at: data.fetched_at.clone(),
+                validators: data.validators.clone(),
+                last_accessed: None,
+                holidays: data.holidays.clone(),
+            }],
+        )?;
+    }
+    Ok(data)
+}
+
+// Like get_holidays, but the caller writes the fetched holidays to the cache
+async fn get_holidays_uncached(country_code: &str, year: i32, today: NaiveDate) -> Result<HolidayData, FetchError> {
+    if let Some(file_holidays) = HOLIDAYS_FILE.get() {
+        let holidays: Vec<Holiday> = file_holidays
+            .iter()
+            .filter(|holiday| holiday.date.year() == year)
+            .cloned()
+            .collect();
+        if holidays.is_empty() {
+            print_info(&format!("No holidays for {} in the holidays file.", year));
+        }
+        return Ok(HolidayData { holidays, source: DataSource::File, provider: None, fetched_at: None, validators: Validators::default() });
+    }
+
+    if NO_CACHE.load(Ordering::Relaxed) {
+        print_verbose(&format!("Not using the cache for {} {} (--no-cache).", country_code, year));
+    } else if REFRESH.load(Ordering::Relaxed) {
+        print_verbose(&format!("Refreshing the cached holidays of {} {} (--refresh).", country_code, year));
+    } else if let Some(cached_data) = check_cache(country_code, year).map_err(|err| FetchError::Other(err.to_string()))? {
+        print_info(&format!("Using cached data for {} {} (Date: {}).", country_code, year, cached_data.date));
+        // Cache was used
+        return Ok(HolidayData {
+            holidays: cached_data.holidays,
+            source: DataSource::Cache,
+            provider: Some(cached_data.provider),
+            fetched_at: cached_data.fetched_at,
+            validators: cached_data.validators,
+        });
+    }
+
+    // Expired entries are requested again with their validators
+    let full_cache = if NO_CACHE.load(Ordering::Relaxed) { None } else { cache::load_cache().ok().flatten() };
+    // The next provider is only tried when one can't be reached or has an outage, other errors are final
+    let providers = providers();
+    let expired = providers.iter().find_map(|options| {
+        full_cache.as_ref()?.data.get(&(options.cache_key(), country_code.to_string(), year))
+    });
+    if let Some(cached_data) = expired.filter(|_| SERVE_STALE.load(Ordering::Relaxed) && !REFRESH.load(Ordering::Relaxed)) {
+        print_verbose(&format!("Using the expired cached data for {} {}, it is requested again after the output.", country_code, year));
+        let age = cached_data.age().unwrap_or_else(cache_ttl);
+        STALE_ENTRIES.lock().unwrap_or_else(|err| err.into_inner()).push((country_code.to_string(), year, age));
+        return Ok(HolidayData {
+            holidays: cached_data.holidays.clone(),
+            source: DataSource::Cache,
+            provider: Some(cached_data.provider.clone()),
+            fetched_at: cached_data.fetched_at.clone(),
+            validators: cached_data.validators.clone(),
+        });
+    }
+    let mut outage = None;
+    for (index, options) in providers.iter().enumerate() {
+        let cached = full_cache
+            .as_ref()
+            .and_then(|full_cache| full_cache.data.get(&(options.cache_key(), country_code.to_string(), year)));
+        match fetch_holidays(options, country_code, year, today, cached).await {
+            Err(err) if err.is_outage() => {
+                if let Some(next_options) = providers.get(index + 1) {
+                    print_info(&format!(
+                        "Could not get the holidays from {} ({}), trying {}.",
+                        options.provider.name(),
+                        err,
+                        next_options.provider.name()
+                    ));
+                }
+                outage = Some(err);
+            }
+            result => return result,
+        }
+    }
+    let err = outage.expect("there is at least one provider");
+    // Without a connection expired holidays from the cache, or else the bundled snapshot, are better than nothing
+    if matches!(err, FetchError::Connect | FetchError::Timeout) && !STRICT.load(Ordering::Relaxed) {
+        if let Some(cached_data) = expired {
+            let date = cached_data.date.to_string();
+            let fetched_on = cached_data.fetched_at.as_deref().and_then(|fetched_at| fetched_at.get(..10)).unwrap_or(&date);
+            eprintln!("Warning: The API can't be reached, showing cached data from {} (offline).", fetched_on);
+            return Ok(HolidayData {
+                holidays: cached_data.holidays.clone(),
+                source: DataSource::Cache,
+                provider: Some(cached_data.provider.clone()),
+                fetched_at: cached_data.fetched_at.clone(),
+                validators: cached_data.validators.clone(),
+            });
+        }
+        if let Some(data) = bundled_holidays(country_code, year) {
+            return Ok(data);
+        }
+    }
+    Err(err)
+}
+
+// Runs after the output, so the next run finds fresh holidays. The cache is written like after any request,
+// and nothing that goes wrong here changes what was printed or the exit code.
+async fn revalidate_stale_entries(today: NaiveDate) {
+    let stale = std::mem::take(&mut *STALE_ENTRIES.lock().unwrap_or_else(|err| err.into_inner()));
+    if stale.is_empty() {
+        return;
+    }
+    SERVE_STALE.store(false, Ordering::Relaxed);
+    // Expired or bundled holidays from the offline fallback would be no update
+    STRICT.store(true, Ordering::Relaxed);
+    // The messages of the requests would only repeat what was printed for the output
+    let quiet = QUIET.swap(true, Ordering::Relaxed);
+    let results: Vec<_> = stream::iter(stale)
+        .map(|(country_code, year, age)| async move {
+            let result = get_holidays_uncached(&country_code, year, today).await;
+            (country_code, year, age, result)
+        })
+        .buffered(4)
+        .collect()
+        .await;
+    QUIET.store(quiet, Ordering::Relaxed);
+
+    let mut new_entries = Vec::new();
+    let mut notes = Vec::new();
+    for (country_code, year, age, result) in results {
+        let stale_by = cache::format_age(age - cache_ttl());
+        match result {
+            Ok(data) if data.source == DataSource::Api && !data.holidays.is_empty() => {
+                new_entries.push((stale_by, CachedData {
+                    provider: data.provider.unwrap_or_else(|| provider_options().cache_key()),
+                    country_code,
+                    year,
+                    date: today,
+                    fetched_at: data.fetched_at,
+                    validators: data.validators,
+                    last_accessed: None,
+                    holidays: data.holidays,
+                }));
+            }
+            Ok(_) => notes.push(format!(
+                "Note: The cached holidays of {} {} were stale by {}, the API has no holidays for it now.",
+                country_code, year, stale_by
+            )),
+            Err(err) => notes.push(format!(
+                "Note: The cached holidays of {} {} were stale by {} and could not be updated ({}).",
+                country_code,
+                year,
+                stale_by,
+                err.reason()
+            )),
+        }
+    }
+    if !new_entries.is_empty() {
+        let updated: Vec<String> = new_entries
+            .iter()
+            .map(|(stale_by, entry)| format!("{} {} were stale by {}", entry.country_code, entry.year, stale_by))
+            .collect();
+        let entries = new_entries.into_iter().map(|(_, entry)| entry).collect();
+        match cache::try_update_cache(today, |full_cache| add_cache_entries(full_cache, today, entries)) {
+            Ok(()) => notes.extend(updated.iter().map(|entry| format!("Note: The cached holidays of {}, the cache is updated.", entry))),
+            Err(err) => notes.extend(
+                updated.iter().map(|entry| format!("Note: The cached holidays of {} and could not be updated ({}).", entry, err)),
+            ),
+        }
+    }
+    for note in notes {
+        print_info(&note);
+    }
+}
+
+// An expired entry in the cache is requested with its validators, and kept when the API answers 304 Not Modified
+async fn fetch_holidays(
+    options: &ProviderOptions,
+    country_code: &str,
+    year: i32,
+    today: NaiveDate,
+    cached: Option<&CachedData>,
+) -> Result<HolidayData, FetchError> {
+    let url = options.holidays_url(country_code, year);
+
+    // Request to API
+    let validators = cached.map(|cached| cached.validators.clone()).unwrap_or_default();
+    let answer = fetch_answer(&url, &validators).await?;
+    let fetched_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    if let Some(cached) = cached.filter(|_| answer.status == reqwest::StatusCode::NOT_MODIFIED) {
+        print_verbose(&format!("The holidays of {} {} have not changed on {}.", country_code, year, options.provider.name()));
+        return Ok(HolidayData {
+            holidays: cached.holidays.clone(),
+            source: DataSource::Api,
+            provider: Some(options.cache_key()),
+            fetched_at: Some(fetched_at),
+            // Servers don't have to repeat the validators in a 304
+            validators: if answer.validators.is_empty() { validators } else { answer.validators },
+        });
+    }
+    let (status, body) = (answer.status, answer.body.as_str());
+    let response = match options.provider {
+        Provider::Nager => parse_json(status, body).map(Option::unwrap_or_default),
+        Provider::Openholidays => parse_json(status, body)
+            .map(|open_holidays| provider::expand_open_holidays(open_holidays.unwrap_or_default(), year)),
+        Provider::Calendarific => match parse_json(status, body) {
+            Ok(Some(response)) => provider::calendarific_holidays(response).map_err(|err| match err.code {
+                429 => FetchError::RateLimited { retry_after: None },
+                _ => FetchError::Provider(format!("Calendarific answered with error {}: {}", err.code, err.message)),
+            }),
+            Ok(None) => Ok(Vec::new()),
+            // Some errors come with their HTTP status as well
+            Err(FetchError::Status(reqwest::StatusCode::UNAUTHORIZED)) => {
+                Err(FetchError::Provider("Calendarific did not accept the API key".to_string()))
+            }
+            Err(err) => Err(err),
+        },
+        Provider::Holidayapi => provider::holidayapi_holidays(status.as_u16(), body, year, today.year())
+            .map_err(|err| FetchError::Provider(format!("holidayapi.com answered with error {}: {}", err.code, err.message))),
+    };
+    let holidays: Vec<Holiday> = match response {
+        // A bare "Not Found" would not say whether the country or the year is the problem
+        Err(FetchError::Status(reqwest::StatusCode::NOT_FOUND)) => {
+            let available_countries = get_available_countries(today).await.unwrap_or_default();
+            return Err(if is_available(country_code, &available_countries) {
+                FetchError::NoData { country_code: country_code.to_string(), year }
+            } else {
+                FetchError::UnknownCountry {
+                    country_code: country_code.to_string(),
+                    supported: available_countries.into_iter().map(|country| country.country_code).collect(),
+                }
+            });
+        }
+        result => result?,
+    };
+    if holidays.is_empty() {
+        print_info(&format!("No {} holidays found for {} in {}.", options.kind.name(), country_code, year));
+    }
+    print_verbose(&format!("Got the holidays of {} {} from {}.", country_code, year, options.provider.name()));
+    Ok(HolidayData {
+        holidays,
+        source: DataSource::Api,
+        provider: Some(options.cache_key()),
+        fetched_at: Some(fetched_at),
+        validators: answer.validators,
+    })
+}
+
+// The holidays from the snapshot of the bundled-data feature, with a warning because they can be outdated
+fn bundled_holidays(country_code: &str, year: i32) -> Option<HolidayData> {
+    let bundled = bundled::bundled_data()?;
+    let data = bundled.data.iter().find(|data| data.is_for(&provider::default_cache_key(), country_code, year))?;
+    eprintln!(
+        "Warning: The API can't be reached, using bundled data from {}, it may be outdated.",
+        bundled.generated_at.as_deref().unwrap_or("an unknown date")
+    );
+    Some(HolidayData {
+        holidays: data.holidays.clone(),
+        source: DataSource::Bundled,
+        provider: Some(data.provider.clone()),
+        fetched_at: data.fetched_at.clone(),
+        validators: Validators::default(),
+    })
+}
+
+// Why the holidays of a country and year could not be requested
+#[derive(Debug)]
+enum FetchError {
+    Status(reqwest::StatusCode),
+    UnknownCountry { country_code: String, supported: Vec<String> }, // 404 for a country the API doesn't list
+    NoData { country_code: String, year: i32 },                     // 404 for a country the API lists
+    RateLimited { retry_after: Option<u64> },                       // 429, in seconds when the API says so
+    Provider(String),                                               // An error the API reported in its answer
+    Connect,
+    Timeout,
+    Other(String),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> FetchError {
+        if err.is_connect() {
+            FetchError::Connect
+        } else if err.is_timeout() {
+            FetchError::Timeout
+        } else {
+            // The URL can contain an API key
+            FetchError::Other(err.without_url().to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FetchError::Status(status) => write!(f, "HTTP status {}", status),
+            FetchError::UnknownCountry { country_code, supported } => write!(
+                f,
+                "The country {} is not supported by the holiday API. Supported codes include {}",
+                country_code,
+                supported.iter().take(10).cloned().collect::<Vec<_>>().join(", ")
+            ),
+            FetchError::NoData { country_code, year } if *year < MIN_YEAR => {
+                write!(f, "No holiday data is published for {} in {}, the data starts at {}", country_code, year, MIN_YEAR)
+            }
+            FetchError::NoData { country_code, year } => {
+                write!(f, "No holiday data is published for {} in {}", country_code, year)
+            }
+            FetchError::RateLimited { retry_after: Some(seconds) } => {
+                write!(f, "The rate limit of the API was reached, try again in {} seconds", seconds)
+            }
+            FetchError::RateLimited { retry_after: None } => write!(f, "The rate limit of the API was reached, try again later"),
+            FetchError::Provider(message) => write!(f, "{}", message),
+            FetchError::Connect => write!(f, "unable to connect to the API"),
+            FetchError::Timeout => write!(f, "the request timed out"),
+            FetchError::Other(detail) => write!(f, "{}", detail),
+        }
+    }
+}
+
+impl FetchError {
+    // The API is down or can't be reached, so another attempt or another provider may work
+    fn is_outage(&self) -> bool {
+        match self {
+            FetchError::Status(status) => status.is_server_error(),
+            FetchError::Connect | FetchError::Timeout => true,
+            _ => false,
+        }
+    }
+
+    // Short form for the summary of failed countries
+    fn reason(&self) -> String {
+        match self {
+            FetchError::Status(status) => status.as_u16().to_string(),
+            FetchError::UnknownCountry { .. } => "not supported".to_string(),
+            FetchError::NoData { year, .. } => format!("no data for {}", year),
+            FetchError::RateLimited { .. } => "rate limited".to_string(),
+            FetchError::Provider(message) => message.clone(),
+            FetchError::Connect => "no connection".to_string(),
+            FetchError::Timeout => "timeout".to_string(),
+            FetchError::Other(detail) => detail.clone(),
+        }
+    }
+}
+
+fn exit_with_fetch_error(err: &FetchError) -> ! {
+    match err {
+        FetchError::Status(status) => handle_http_error(*status),
+        FetchError::UnknownCountry { .. }
+        | FetchError::NoData { .. }
+        | FetchError::RateLimited { .. }
+        | FetchError::Provider(_) => eprintln!("Error: {}.", err),
+        FetchError::Connect => {
+            eprintln!("Network error: Unable to connect to the API. Please check your internet connection.")
+        }
+        FetchError::Timeout => eprintln!("Request timed out: Please try again later."),
+        FetchError::Other(detail) => eprintln!("Unexpected error occurred while connecting to the API: {}", detail),
+    }
+    exit_with_error();
+}
+
+// The worldwide holidays are cached for WORLDWIDE_CACHE_HOURS, they change with every day
+async fn get_worldwide_holidays() -> Result<Vec<WorldwideHoliday>, Box<dyn std::error::Error>> {
+    let today = Local::now().date_naive();
+    create_cache_if_missing(today)?;
+    let full_cache = cache::load_cache().ok().flatten().unwrap_or_else(|| Arc::new(FullCache::new(today)));
+    if let Some(cached) = &full_cache.worldwide {
+        if fetched_within(&cached.fetched_at, Duration::hours(WORLDWIDE_CACHE_HOURS)) {
+            print_info(&format!("Using cached worldwide holidays (fetched at {}).", cached.fetched_at));
+            return Ok(cached.holidays.clone());
+        }
+    }
+
+    let url = format!("{}/api/v3/NextPublicHolidaysWorldwide", api_url());
+    let mut holidays: Vec<WorldwideHoliday> = fetch_list(&url).await.unwrap_or_else(|err| exit_with_fetch_error(&err));
+    holidays.sort_by(|a, b| a.holiday.date.cmp(&b.holiday.date).then_with(|| a.country_code.cmp(&b.country_code)));
+
+    cache::update_cache(today, |full_cache| {
+        full_cache.worldwide = Some(WorldwideCache {
+            fetched_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            holidays: holidays.clone(),
+        });
+    })?;
+    Ok(holidays)
+}
+
+// Some endpoints answer 204 No Content or send an empty body when there is no data, that is left to the caller
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<Option<T>, FetchError> {
+    let (status, body) = fetch_body(url).await?;
+    parse_json(status, &body)
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(status: reqwest::StatusCode, body: &str) -> Result<Option<T>, FetchError> {
+    if !status.is_success() {
+        return Err(FetchError::Status(status));
+    }
+    if status == reqwest::StatusCode::NO_CONTENT || body.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(body)
+        .map(Some)
+        .map_err(|err| FetchError::Other(format!("the API sent an unexpected response: {}", err)))
+}
+
+// The status and body of any answer except a rate limit, for APIs that explain their errors in the body.
+// Outages are retried FETCH_RETRIES times with a growing pause, because they are often over quickly.
+async fn fetch_body(url: &str) -> Result<(reqwest::StatusCode, String), FetchError> {
+    fetch_answer(url, &Validators::default()).await.map(|answer| (answer.status, answer.body))
+}
+
+// An answer of the API, with the validators to send with the next request
+struct Answer {
+    status: reqwest::StatusCode,
+    body: String,
+    validators: Validators,
+}
+
+// Like fetch_body, the validators make it a conditional request
+async fn fetch_answer(url: &str, validators: &Validators) -> Result<Answer, FetchError> {
+    let mut attempt = 0;
+    loop {
+        let result = fetch_answer_once(url, validators).await;
+        let failed = match &result {
+            Ok(answer) => answer.status.is_server_error(),
+            Err(err) => err.is_outage(),
+        };
+        if !failed || attempt == FETCH_RETRIES {
+            return result;
+        }
+        attempt += 1;
+        print_verbose(&format!("The request failed, retrying ({} of {}).", attempt, FETCH_RETRIES));
+        tokio::time::sleep(std::time::Duration::from_millis(500 * attempt as u64)).await;
+    }
+}
+
+async fn fetch_answer_once(url: &str, validators: &Validators) -> Result<Answer, FetchError> {
+    let mut request = http_client().get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        return Err(FetchError::RateLimited { retry_after });
+    }
+    let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+    let validators = Validators { etag: header(reqwest::header::ETAG), last_modified: header(reqwest::header::LAST_MODIFIED) };
+    Ok(Answer { status, body: response.text().await?, validators })
+}
+
+// A list endpoint without data is an empty list
+async fn fetch_list<T: serde::de::DeserializeOwned>(url: &str) -> Result<Vec<T>, FetchError> {
+    Ok(fetch_json(url).await?.unwrap_or_default())
+}
+
+// Set once from --api-url before the first request
+static API_URL: OnceLock<String> = OnceLock::new();
+
+fn api_url() -> &'static str {
+    API_URL.get().map(String::as_str).unwrap_or(DEFAULT_API_URL)
+}
+
+// Set once from --cache-ttl or the config file
+static CACHE_TTL: OnceLock<Duration> = OnceLock::new();
+
+fn cache_ttl() -> Duration {
+    CACHE_TTL.get().copied().unwrap_or(Duration::days(CACHE_TTL_DAYS))
+}
+
+// Set once from --max-cache-entries or the config file
+static MAX_CACHE_ENTRIES: OnceLock<usize> = OnceLock::new();
+
+fn max_cache_entries() -> usize {
+    MAX_CACHE_ENTRIES.get().copied().unwrap_or(MAX_CACHE_ENTRIES_DEFAULT)
+}
+
+// Set once from --cache-dir, or the platform cache directory. With a cache file it is the directory of the file.
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Set once from --cache-file or GET_HOLIDAY_CACHE, the whole cache is then kept in that one file
+static CACHE_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+// The directory of a chosen cache file has to exist unless --create-dirs is given, the cache directory is always created
+fn set_cache_location(args: &Args) {
+    let cache_file = args
+        .cache_file
+        .clone()
+        .or_else(|| std::env::var_os(CACHE_FILE_ENV).filter(|value| !value.is_empty()).map(PathBuf::from));
+    let Some(cache_file) = cache_file else {
+        CACHE_DIR.set(args.cache_dir.clone().unwrap_or_else(default_cache_dir)).expect("the cache directory is only set here");
+        move_legacy_cache();
+        return;
+    };
+    let directory = match cache_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if !directory.exists() && !NO_CACHE.load(Ordering::Relaxed) {
+        if !args.create_dirs {
+            eprintln!(
+                "Error: The directory '{}' of the cache file does not exist. Use '--create-dirs' to create it.",
+                directory.display()
+            );
+            exit_with_error();
+        }
+        if let Err(err) = fs::create_dir_all(&directory) {
+            handle_file_error(&err, &directory.display().to_string());
+            exit_with_error();
+        }
+    }
+    CACHE_DIR.set(directory).expect("the cache directory is only set here");
+    CACHE_FILE.set(cache_file).expect("the cache file is only set here");
+}
+
+// $XDG_CACHE_HOME/get_holiday on Linux, the working directory when the platform has no cache directory
+fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir().map(|dir| dir.join("get_holiday")).unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn cache_dir() -> &'static Path {
+    CACHE_DIR.get().map(PathBuf::as_path).unwrap_or(Path::new("."))
+}
+
+// The file the cache is written to, the other one is still read after compression is turned on or off.
+// A chosen cache file keeps its name, it is compressed or not by its content.
+fn cache_file() -> PathBuf {
+    if let Some(cache_file) = CACHE_FILE.get() {
+        return cache_file.clone();
+    }
+    let file_name = match (cache_format(), COMPRESS_CACHE.load(Ordering::Relaxed)) {
+        (CacheFormat::Json, false) => DEFAULT_CACHE_FILE,
+        (CacheFormat::Json, true) => DEFAULT_COMPRESSED_CACHE_FILE,
+        (CacheFormat::Binary, false) => DEFAULT_BINARY_CACHE_FILE,
+        (CacheFormat::Binary, true) => DEFAULT_COMPRESSED_BINARY_CACHE_FILE,
+    };
+    cache_dir().join(file_name)
+}
+
+// Every file the cache can be in, one for each --compress-cache and --cache-format setting
+fn default_cache_files() -> Vec<PathBuf> {
+    [DEFAULT_CACHE_FILE, DEFAULT_COMPRESSED_CACHE_FILE, DEFAULT_BINARY_CACHE_FILE, DEFAULT_COMPRESSED_BINARY_CACHE_FILE]
+        .into_iter()
+        .map(|file_name| cache_dir().join(file_name))
+        .collect()
+}
+
+// The country codes can be put next to the cache, the file in the working directory is used otherwise
+fn country_codes_file() -> PathBuf {
+    let path = cache_dir().join(COUNTRY_CODES_FILE);
+    if path.exists() { path } else { PathBuf::from(COUNTRY_CODES_FILE) }
+}
+
+// Older versions kept the cache in the working directory. It is copied to the cache directory once, so the
+// cached holidays are not requested again.
+fn move_legacy_cache() {
+    // Errors show up when the cache is written
+    let _ = fs::create_dir_all(cache_dir());
+    let path = cache_dir().join(DEFAULT_CACHE_FILE);
+    let legacy_path = Path::new(DEFAULT_CACHE_FILE);
+    if default_cache_files().iter().any(|path| path.exists()) || !legacy_path.exists() {
+        return;
+    }
+    if fs::copy(legacy_path, &path).is_ok() {
+        print_info(&format!(
+            "Warning: {} in the working directory is deprecated, it was copied to {} and can be deleted.",
+            DEFAULT_CACHE_FILE,
+            path.display()
+        ));
+    }
+}
+
+// Set once from --holidays-file, then every year is taken from it instead of the cache or the API
+static HOLIDAYS_FILE: OnceLock<Vec<Holiday>> = OnceLock::new();
+
+// Set once from --provider or the config file before the first request, the first one is tried first
+static PROVIDERS: OnceLock<Vec<ProviderOptions>> = OnceLock::new();
+
+fn providers() -> &'static [ProviderOptions] {
+    PROVIDERS.get().expect("the providers are set at the start of main")
+}
+
+// The provider that is tried first
+fn provider_options() -> &'static ProviderOptions {
+    &providers()[0]
+}
+
+// With --provider only that provider is used, otherwise the providers of the config file in their order
+fn provider_list(args: &Args, config_file: &ConfigFile) -> Vec<ProviderOptions> {
+    let mut providers: Vec<Provider> = match args.provider {
+        Some(provider) => vec![provider],
+        None if !config_file.providers.is_empty() => config_file.providers.clone(),
+        None => vec![Provider::Nager],
+    };
+    let mut seen = BTreeSet::new();
+    providers.retain(|provider| seen.insert(provider.name()));
+
+    providers
+        .into_iter()
+        .enumerate()
+        .map(|(index, provider)| {
+            if args.kind == HolidayKind::School && !provider.has_school_holidays() {
+                eprintln!("Error: '--kind school' is only available with '--provider openholidays'.");
+                exit_with_error();
+            }
+            if args.subdivision.is_some() && !provider.has_subdivisions() {
+                eprintln!("Error: '--subdivision' is not available with '--provider {}', use '--county' instead.", provider.name());
+                exit_with_error();
+            }
+            // The environment variable wins, so a key in a shared config file can be overridden
+            let api_key = provider.api_key_names().map(|(env_var, config_key)| {
+                let config_value = match provider {
+                    Provider::Calendarific => config_file.calendarific_api_key.clone(),
+                    Provider::Holidayapi => config_file.holidayapi_api_key.clone(),
+                    Provider::Nager | Provider::Openholidays => None,
+                };
+                std::env::var(env_var).ok().or(config_value).filter(|api_key| !api_key.trim().is_empty()).unwrap_or_else(|| {
+                    eprintln!(
+                        "Error: '--provider {}' needs an API key in {} or as {} in {}.",
+                        provider.name(),
+                        env_var,
+                        config_key,
+                        CONFIG_FILE
+                    );
+                    exit_with_error();
+                })
+            });
+            let base_url = args
+                .api_url
+                .as_ref()
+                .filter(|_| index == 0)
+                .or(config_file.api_urls.get(provider.name()))
+                .map(String::as_str)
+                .unwrap_or(provider.default_url());
+            ProviderOptions {
+                provider,
+                kind: args.kind,
+                subdivision: args.subdivision.as_ref().map(|subdivision| subdivision.trim().to_uppercase()),
+                base_url: base_url.trim_end_matches('/').to_string(),
+                api_key,
+            }
+        })
+        .collect()
+}
+
+// One client for all requests, so connections to the API are reused
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+// The countries supported by the API. They rarely change, so they are cached for COUNTRIES_CACHE_DAYS.
+// Without a connection the codes in COUNTRY_CODES_FILE are used.
+async fn get_available_countries(today: NaiveDate) -> Result<Vec<AvailableCountry>, Box<dyn std::error::Error>> {
+    let full_cache = cache::load_cache().ok().flatten();
+    if let Some(cached) = full_cache.as_ref().and_then(|full_cache| full_cache.countries.as_ref()) {
+        if fetched_within(&cached.fetched_at, Duration::days(COUNTRIES_CACHE_DAYS)) {
+            return Ok(cached.countries.clone());
+        }
+    }
+
+    match fetch_list::<AvailableCountry>(&format!("{}/api/v3/AvailableCountries", api_url())).await {
+        // An empty list would make every country code invalid
+        Ok(countries) if !countries.is_empty() => {
+            cache::update_cache(today, |full_cache| {
+                full_cache.countries = Some(CountriesCache {
+                    fetched_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                    countries: countries.clone(),
+                });
+            })?;
+            Ok(countries)
+        }
+        result => {
+            let reason = result.err().map(|err| err.to_string()).unwrap_or("the API sent no countries".to_string());
+            if let Some(bundled) = bundled::bundled_data() {
+                print_verbose(&format!("Could not get the available countries ({}), using the bundled data.", reason));
+                let country_codes: BTreeSet<&String> = bundled.data.iter().map(|data| &data.country_code).collect();
+                return Ok(country_codes
+                    .into_iter()
+                    .map(|country_code| AvailableCountry { country_code: country_code.clone(), name: String::new() })
+                    .collect());
+            }
+            print_verbose(&format!("Could not get the available countries ({}), using {}.", reason, COUNTRY_CODES_FILE));
+            Ok(read_country_codes()?
+                .into_iter()
+                .map(|country_code| AvailableCountry { country_code, name: String::new() })
+                .collect())
+        }
+    }
+}
+
+// The country with its neighbours down to `depth` levels. A country appears only once
+// on every path, so the neighbours of Austria below Germany don't list Germany again.
+async fn get_country_info_tree(
+    country_code: &str,
+    depth: usize,
+    today: NaiveDate,
+) -> Result<CountryInfo, Box<dyn std::error::Error>> {
+    let mut root = get_country_info(country_code, today).await?;
+    if depth == 0 {
+        root.borders = None;
+        return Ok(root);
+    }
+    let mut path = vec![root.country_code.clone()];
+    expand_borders(&mut root, depth, &mut path, today).await?;
+    Ok(root)
+}
 
-#[derive(Parser, Debug)]
-struct Args { 
-    // Country Code
-    country: String,
+// The borders returned by the API have no borders of their own, so every deeper level needs more requests
+async fn expand_borders(
+    info: &mut CountryInfo,
+    depth: usize,
+    path: &mut Vec<String>,
+    today: NaiveDate,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(borders) = info.borders.as_mut() else {
+        return Ok(());
+    };
+    borders.retain(|border| !path.contains(&border.country_code));
+    for border in borders.iter_mut() {
+        border.borders = None;
+        if depth > 1 {
+            *border = get_country_info(&border.country_code, today).await?;
+            path.push(border.country_code.clone());
+            Box::pin(expand_borders(border, depth - 1, path, today)).await?;
+            path.pop();
+        }
+    }
+    Ok(())
 }
 
-#[derive(Deserialize, Serialize,  Debug, Clone)]
-struct Holiday { 
-    date: String,
-    name: String,
-    counties: Option<Vec<String>>, // Counties information is optional
-    types: Vec<String>,
+// The information about a country hardly ever changes, so it stays in the cache
+async fn get_country_info(country_code: &str, today: NaiveDate) -> Result<CountryInfo, Box<dyn std::error::Error>> {
+    let full_cache = cache::load_cache().ok().flatten().unwrap_or_else(|| Arc::new(FullCache::new(today)));
+    if let Some(info) = full_cache.country_info.get(country_code) {
+        print_verbose(&format!("Using cached country information for {}.", country_code));
+        return Ok(info.clone());
+    }
+
+    let url = format!("{}/api/v3/CountryInfo/{}", api_url(), country_code);
+    let info: CountryInfo = match fetch_json(&url).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            eprintln!("Error: The API has no information about {}.", country_code);
+            exit_with_error();
+        }
+        Err(err) => exit_with_fetch_error(&err),
+    };
+    cache::update_cache(today, |full_cache| {
+        full_cache.country_info.insert(country_code.to_string(), info.clone());
+    })?;
+    Ok(info)
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct CachedData { 
-    country_code: String,
-    date: String, 
-    holidays: Vec<Holiday>,
+async fn get_long_weekends(country_code: &str, year: i32, today: NaiveDate) -> Result<Vec<LongWeekend>, Box<dyn std::error::Error>> {
+    create_cache_if_missing(today)?;
+    let full_cache = cache::load_cache().ok().flatten().unwrap_or_else(|| Arc::new(FullCache::new(today)));
+    if let Some(cached) = full_cache.long_weekends.iter().find(|cached| {
+        cached.country_code == country_code
+            && cached.year == year
+            && cached.fetched_at.as_deref().is_some_and(|fetched_at| fetched_within(fetched_at, cache_ttl()))
+    }) {
+        print_info(&format!("Using cached long weekends for {} {} (Date: {}).", country_code, year, today));
+        return Ok(cached.long_weekends.clone());
+    }
+
+    let url = format!("{}/api/v3/LongWeekend/{}/{}", api_url(), year, country_code);
+    let long_weekends: Vec<LongWeekend> = fetch_list(&url).await.unwrap_or_else(|err| exit_with_fetch_error(&err));
+    if long_weekends.is_empty() {
+        return Ok(long_weekends);
+    }
+    cache::update_cache(today, |full_cache| {
+        full_cache.long_weekends.retain(|cached| cached.country_code != country_code || cached.year != year);
+        full_cache.long_weekends.push(CachedLongWeekends {
+            country_code: country_code.to_string(),
+            year,
+            fetched_at: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            long_weekends: long_weekends.clone(),
+        });
+    })?;
+    Ok(long_weekends)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct FullCache {
-    date: String,             
-    data: Vec<CachedData>,    
+// Whether an RFC 3339 timestamp from the cache is less than `max_age` ago
+fn fetched_within(fetched_at: &str, max_age: Duration) -> bool {
+    chrono::DateTime::parse_from_rfc3339(fetched_at)
+        .is_ok_and(|fetched_at| Utc::now() - fetched_at.with_timezone(&Utc) < max_age)
 }
 
-const CACHE_FILE: &str = "holidays_cache.json" ; // cache file where data will be saved
-const COUNTRY_CODES_FILE: &str = "country_codes.txt"; // Name of the file containing country codes
+// Errors name the position and field of the holiday, e.g. "[2].date: invalid date '2026-02-30'"
+fn read_holidays_file(path: &str) -> Result<Vec<Holiday>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let holidays: Vec<Holiday> =
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&content)).map_err(|err| err.to_string())?;
+    Ok(holidays)
+}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>>  {
-    let args = Args::parse();
-    let country_code = args.country.to_uppercase();
-    let valid_country_codes = read_country_codes().expect("Failed to read country codes file");
+fn read_country_codes() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let path = country_codes_file();
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()),
+        Err(err) => {
+            handle_file_error(&err, &path.display().to_string());
+            Err(Box::new(err)) 
+        }
+    }
+}
 
-    if !valid_country_codes.contains(&country_code) {
-        eprintln!(
-            "Error: '{}' is not a valid country code. Valid country codes are: {:?}",
-            country_code, valid_country_codes
+fn check_cache(country_code: &str, year: i32) -> Result<Option<CachedData>, Box<dyn std::error::Error>> {
+    match cache::load_cache() {
+        Ok(Some(full_cache)) => {
+            // The holidays may have come from a fallback provider
+            for options in providers() {
+                let key = (options.cache_key(), country_code.to_string(), year);
+                if let Some(cached_data) = full_cache.data.get(&key).filter(|data| data.is_fresh()) {
+                    // Only written once an hour, most lookups don't change the cache. A lookup never waits for it.
+                    if cached_data.last_accessed.as_deref().is_none_or(|last_accessed| !fetched_within(last_accessed, Duration::hours(1))) {
+                        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+                        cache::update_cache_if_unlocked(|full_cache| {
+                            if let Some(data) = full_cache.data.get_mut(&key) {
+                                data.last_accessed = Some(now);
+                            }
+                        });
+                    }
+                    return Ok(Some(cached_data.clone()));
+                }
+            }
+        }
+        Ok(None) => {
+            print_info("Warning: Cache file could not be opened or does not exist. Proceeding with API request.");
+        }
+        Err(err) => print_info(&format!("Warning: Cache file exists but could not be parsed ({}). Ignoring cache.", err)),
+    }
+
+    Ok(None) 
+}
+
+// Meant to run on every shell prompt: a cold cache prints nothing instead of waiting for the API,
+// and the country is not validated because only a valid country can be in the cache
+fn print_prompt(country_code: &str, year: Option<i32>, format: &str, language: Language) {
+    QUIET.store(true, Ordering::Relaxed);
+    let today = Local::now().date_naive();
+    let year = year.unwrap_or(today.year());
+    let Some(mut holidays) = prompt_holidays(country_code, year) else {
+        return;
+    };
+    // After the last holiday of the year the next one is in January, if that year is cached too
+    if year == today.year() && !has_holiday_after(&holidays, today) {
+        holidays.extend(prompt_holidays(country_code, year + 1).unwrap_or_default());
+    }
+
+    let next_holiday = holidays
+        .iter()
+        .map(|holiday| (holiday.date, holiday))
+        .filter(|(date, _)| *date >= today)
+        .min_by_key(|(date, _)| *date);
+    if let Some((date, holiday)) = next_holiday {
+        println!(
+            "{}",
+            format
+                .replace("{days}", &(date - today).num_days().to_string())
+                .replace("{name}", &holiday.display_name(language))
+                .replace("{date}", &holiday.date.to_string())
         );
-        std::process::exit(1);
     }
-    
-    let today = Local::now().date_naive(); 
-    let current_year = Local::now().year();
+}
+
+// The cached holidays of any age, expired ones are still right for a prompt. The cache is only read, so the
+// prompt never waits for the lock.
+fn prompt_holidays(country_code: &str, year: i32) -> Option<Vec<Holiday>> {
+    let full_cache = cache::peek_cache()?;
+    providers()
+        .iter()
+        .find_map(|options| full_cache.data.get(&(options.cache_key(), country_code.to_string(), year)))
+        .map(|cached_data| cached_data.holidays.clone())
+}
 
-    reset_cache_if_needed(today)?; //  If the date of the cache file and today's date are different, it clears the file.
+fn has_holiday_after(holidays: &[Holiday], today: NaiveDate) -> bool {
+    holidays.iter().any(|holiday| holiday.date > today)
+}
 
-    if let Some(cached_data) = check_cache(&country_code, today)? {
-        println!("Using cached data for {} (Date: {}).", country_code, today);
-        list_holidays(&cached_data.holidays, today, &country_code).await?;
-                return Ok(()); // Cache was used
+// Late in December nothing may be left of the current year, then the next holiday is in January
+async fn with_next_year_if_needed(
+    holidays: &[Holiday],
+    country_code: &str,
+    year: i32,
+    today: NaiveDate,
+) -> Result<Vec<Holiday>, Box<dyn std::error::Error>> {
+    let mut holidays = holidays.to_vec();
+    if year == today.year() && !has_holiday_after(&holidays, today) {
+        print_info(&format!("No holidays remain this year, looking at {}.", year + 1));
+        holidays.extend(get_holidays(country_code, year + 1, today).await?.holidays);
     }
+    Ok(holidays)
+}
 
-    let url = format!("https://date.nager.at/api/v3/publicholidays/{}/{}", current_year, country_code); 
+// False when no holiday is left, the caller exits with EXIT_NO_HOLIDAYS
+fn print_next_holiday(holidays: &[Holiday], today: NaiveDate, country_code: &str, language: Language) -> bool {
+    let next_holiday = holidays
+        .iter()
+        .map(|holiday| (holiday.date, holiday))
+        .filter(|(date, _)| *date > today)
+        .min_by_key(|(date, _)| *date);
 
-    // Request to API
-    match reqwest::get(&url).await {
-        Ok(response) => {
-            if response.status().is_success() {
-                let holidays: Vec<Holiday> = response.json().await?;
-                write_cache(&country_code, today, &holidays)?;
-                list_holidays(&holidays, today, &country_code).await?;
-            } else {
-                handle_http_error(response.status());
-            }
+    match next_holiday {
+        Some((date, holiday)) => {
+            let days = (date - today).num_days();
+            println!(
+                "Next holiday in {}: {} ({}), in {} {}.",
+                country_code,
+                holiday.display_name(language),
+                holiday.date,
+                days,
+                if days == 1 { "day" } else { "days" }
+            );
+            true
         }
-        Err(err) => {
-            if err.is_connect() {
-                eprintln!("Network error: Unable to connect to the API. Please check your internet connection.");
-            } else if err.is_timeout() {
-                eprintln!("Request timed out: Please try again later.");
-            } else {
-                eprintln!("Unexpected error occurred while connecting to the API: {}", err);
-            }
-            std::process::exit(1);
+        None => {
+            println!("No upcoming holidays in {}.", country_code);
+            false
         }
     }
+}
 
-    Ok(())
+// False when the date is not a holiday, the caller exits with EXIT_NOT_A_HOLIDAY. Errors exit with EXIT_ANSWER_ERROR.
+fn print_holidays_on(holidays: &[Holiday], date: NaiveDate, country_code: &str, language: Language) -> bool {
+    let holidays_on_date: Vec<&Holiday> = holidays.iter().filter(|holiday| holiday.date == date).collect();
+    if holidays_on_date.is_empty() {
+        println!("{} is not a holiday in {}.", date, country_code);
+        return false;
+    }
+    for holiday in holidays_on_date {
+        println!(
+            "{} is a holiday in {}: {} ({}), {}",
+            date,
+            country_code,
+            holiday.display_name(language),
+            holiday.types.join(", "),
+            holiday.counties_text()
+        );
+    }
+    true
 }
 
-fn read_country_codes() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    match fs::read_to_string(COUNTRY_CODES_FILE) {
-        Ok(content) => Ok(content.lines().map(|line| line.trim().to_string()).collect()),
-        Err(err) => {
-            handle_file_error(&err, COUNTRY_CODES_FILE);
-            Err(Box::new(err)) 
+// Returns the number of listed holidays, so the exit status can be chosen once every country is listed
+async fn list_holidays(holidays: &[Holiday], config: &ListConfig, several_countries: bool) -> Result<usize, Box<dyn std::error::Error>> {
+    let selected_holidays = select_holidays(holidays, config).await?;
+
+    if config.count {
+        // With several countries every count is labelled with its country
+        match (config.output, several_countries) {
+            (OutputFormat::Json, false) => println!("{}", serde_json::json!({ "count": selected_holidays.len() })),
+            (OutputFormat::Json, true) => {
+                println!("{}", serde_json::json!({ "country": config.country_code, "count": selected_holidays.len() }))
+            }
+            (_, false) => println!("{}", selected_holidays.len()),
+            (_, true) => println!("{}\t{}", config.country_code, selected_holidays.len()),
         }
+        return Ok(selected_holidays.len());
     }
+
+    match &config.output_file {
+        Some(path) => {
+            write_file_atomically(Path::new(path), config.create_dirs, |file| {
+                output::write_holidays(file, &selected_holidays, config)
+            })?;
+            print_info(&format!("Wrote {} holidays to {}", selected_holidays.len(), path));
+        }
+        None => output::write_holidays(&mut std::io::stdout().lock(), &selected_holidays, config)?,
+    }
+
+    print_listed_count(selected_holidays.len(), config);
+    Ok(selected_holidays.len())
 }
 
-fn check_cache(country_code: &str, today: NaiveDate) -> Result<Option<CachedData>, Box<dyn std::error::Error>> {
-    if let Ok(cache_content) = fs::read_to_string(CACHE_FILE) {
-        if let Ok(full_cache) = serde_json::from_str::<FullCache>(&cache_content) {
-            if let Some(cached_data) = full_cache.data.iter().find(|data| {
-                data.country_code == country_code && data.date == today.to_string()
-            }) {
-                return Ok(Some(cached_data.clone()));
+// Count line with --all, so it is visible that nothing was truncated
+fn print_listed_count(holidays_listed: usize, config: &ListConfig) {
+    if config.all {
+        match config.period {
+            Period::Upcoming => print_info(&format!("{} upcoming holidays for {}", holidays_listed, config.country_code)),
+            Period::Past => print_info(&format!("{} past holidays for {}", holidays_listed, config.country_code)),
+            Period::WholeYear => print_info(&format!("{} holidays for {}", holidays_listed, config.country_code)),
+            Period::Month(month) => print_info(&format!(
+                "{} holidays in {} for {}",
+                holidays_listed,
+                NaiveDate::from_ymd_opt(config.today.year(), month, 1).map(|date| date.format("%B").to_string()).unwrap_or_default(),
+                config.country_code
+            )),
+            Period::Range(from, to) => {
+                print_info(&format!("{} holidays from {} to {} for {}", holidays_listed, from, to, config.country_code))
             }
-        } else {
-            eprintln!("Warning: Cache file exists but could not be parsed. Ignoring cache.");
         }
-    } else {
-        eprintln!("Warning: Cache file could not be opened or does not exist. Proceeding with API request.");
     }
-
-    Ok(None) 
 }
 
-async fn list_holidays(holidays: &[Holiday], today: NaiveDate, country_code: &str,) -> Result<(), Box<dyn std::error::Error>> {
-    let filtered_holidays: Vec<&Holiday> = holidays
+async fn select_holidays(holidays: &[Holiday], config: &ListConfig) -> Result<Vec<Holiday>, Box<dyn std::error::Error>> {
+    let limit = if config.all || config.slice.is_some() { holidays.len() } else { config.limit };
+    let mut selected_holidays: Vec<Holiday> = holidays
         .iter()
-        .filter(|holiday| {
-            NaiveDate::parse_from_str(&holiday.date, "%Y-%m-%d")
-                .map(|date| date > today)
-                .unwrap_or(false)
-        })
-        .take(5)
+        .filter(|holiday| in_period(holiday, config) && matches_filters(holiday, config))
+        .cloned()
         .collect();
 
-    for holiday in &filtered_holidays {
-        print_holiday(holiday); 
+    // Left out before the limit, so --limit still lists that many holidays
+    let listed_holidays = selected_holidays.len();
+    selected_holidays.retain(|holiday| on_chosen_days(holiday, config));
+    let omitted_holidays = listed_holidays - selected_holidays.len();
+    if omitted_holidays > 0 {
+        print_info(&format!(
+            "{} {} on a {} and {} omitted.",
+            omitted_holidays,
+            if omitted_holidays == 1 { "holiday falls" } else { "holidays fall" },
+            if matches!(config.day_filter, Some(DayFilter::Weekends(_))) { "working day" } else { "weekend" },
+            if omitted_holidays == 1 { "was" } else { "were" }
+        ));
     }
 
-    let holidays_printed = filtered_holidays.len();
-    let remaining_holidays = 5 - holidays_printed;
+    sort_holidays(&mut selected_holidays, config, config.period == Period::Past);
+    selected_holidays.truncate(limit);
+
+    let remaining_holidays = limit - selected_holidays.len();
     
-    if remaining_holidays > 0 {
-        let next_year = today.year() + 1;
-        let url = format!(
-            "https://date.nager.at/api/v3/publicholidays/{}/{}",
-            next_year, country_code
-        );
-        let response = reqwest::get(&url).await?;
-
-        if response.status().is_success() {
-            let additional_holidays: Vec<Holiday> = response.json().await?;
-            let additional_holidays = additional_holidays
-                .iter()
-                .take(cmp::min(remaining_holidays, additional_holidays.len()))
-                .cloned()
-                .collect::<Vec<_>>();
-            write_cache(
-                country_code, 
-                NaiveDate::from_ymd_opt(next_year, 1, 1).expect("Invalid date"), 
-                &additional_holidays
-            )?;
-
-            for holiday in &additional_holidays {
-                print_holiday(holiday);
-            }
+    // The shortfall is only completed from the next year when listing upcoming holidays
+    if !config.all && config.slice.is_none() && config.period == Period::Upcoming && remaining_holidays > 0 {
+        print_info(&format!("Only {} holidays remain this year.", selected_holidays.len()));
+        let next_year = config.today.year() + 1;
+        let mut additional_holidays = get_holidays(&config.country_code, next_year, config.today).await?.holidays;
+        additional_holidays.retain(|holiday| matches_filters(holiday, config) && on_chosen_days(holiday, config));
+        sort_holidays(&mut additional_holidays, config, false);
+        selected_holidays.extend(additional_holidays.into_iter().take(remaining_holidays));
+        sort_holidays(&mut selected_holidays, config, false);
+    }
 
-        } else {
-            handle_http_error(response.status());
+    match config.slice {
+        Some(Slice::First(count)) => selected_holidays.truncate(count),
+        Some(Slice::Last(count)) => {
+            selected_holidays.drain(..selected_holidays.len().saturating_sub(count));
         }
+        None => {}
     }
 
-    Ok(())
+    // Reversing happens after the limit, so the same holidays are listed, only in the opposite order
+    if config.reverse {
+        selected_holidays.reverse();
+    }
+
+    Ok(selected_holidays)
+}
+
+fn in_period(holiday: &Holiday, config: &ListConfig) -> bool {
+    let date = holiday.date;
+    match config.period {
+        Period::Upcoming => date >= config.today,
+        Period::Past => date < config.today,
+        Period::WholeYear => true,
+        Period::Month(month) => date.month() == month,
+        Period::Range(from, to) => from <= date && date <= to,
+    }
+}
+
+fn on_chosen_days(holiday: &Holiday, config: &ListConfig) -> bool {
+    let weekday = holiday.date.weekday();
+    match config.day_filter {
+        Some(DayFilter::Workdays(workweek)) => workweek.contains(weekday),
+        Some(DayFilter::Weekends(workweek)) => !workweek.contains(weekday),
+        None => true,
+    }
+}
+
+// The filters chosen on the command line, a holiday is listed when it passes all of them
+fn matches_filters(holiday: &Holiday, config: &ListConfig) -> bool {
+    // National holidays apply to every county
+    let county_matches = config.counties.is_empty()
+        || holiday.is_national()
+        || holiday.counties.as_ref().is_some_and(|counties| {
+            counties.iter().any(|county| config.counties.contains(&county.to_uppercase()))
+        });
+    // A holiday that is both public and a school holiday stays when school holidays are excluded
+    let type_excluded = !config.excluded_types.is_empty()
+        && holiday.types.iter().all(|holiday_type| config.excluded_types.contains(&holiday_type.to_lowercase()));
+    // Holidays from older cache entries are left out, because it is unknown whether they are fixed
+    let fixed_matches = config.fixed.is_none() || holiday.fixed == config.fixed;
+    let national_matches = !config.national_only || holiday.is_national();
+    let type_matches = config.types.is_empty()
+        || holiday.types.iter().any(|holiday_type| config.types.contains(&holiday_type.to_lowercase()));
+    let names = || std::iter::once(&holiday.name).chain(&holiday.local_name);
+    let search_matches = config.search.is_empty()
+        || config.search.iter().any(|term| names().any(|name| name.to_lowercase().contains(term)));
+    // --invert-match only turns around the regular expression, the other filters still have to match
+    let regex_matches = config
+        .name_regex
+        .as_ref()
+        .is_none_or(|regex| names().any(|name| regex.is_match(name)) != config.invert_match);
+    let ignore_matches = config.show_ignored || !is_ignored(holiday, config);
+    county_matches
+        && national_matches
+        && fixed_matches
+        && type_matches
+        && !type_excluded
+        && search_matches
+        && regex_matches
+        && ignore_matches
+}
+
+fn is_ignored(holiday: &Holiday, config: &ListConfig) -> bool {
+    config.ignored.iter().any(|rule| rule.matches(holiday, &config.country_code))
 }
 
+// Sorts by the chosen key, holidays with the same key stay in date order.
+// Names are compared in the language they are printed in.
+fn sort_holidays(holidays: &mut [Holiday], config: &ListConfig, newest_first: bool) {
+    holidays.sort_by(|a, b| {
+        let by_key = match config.sort {
+            SortKey::Date => cmp::Ordering::Equal,
+            SortKey::Name => name_sort_key(&a.display_name(config.language))
+                .cmp(&name_sort_key(&b.display_name(config.language))),
+            SortKey::Type => type_rank(a).cmp(&type_rank(b)),
+        };
+        let by_date = if newest_first { b.date.cmp(&a.date) } else { a.date.cmp(&b.date) };
+        by_key.then(by_date)
+    });
+}
+
+// Case and accent insensitive, so "Épiphanie" sorts next to "Easter"
+fn name_sort_key(name: &str) -> String {
+    name.nfd()
+        .filter(|character| !is_combining_mark(*character))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
 
-fn write_cache(country_code: &str, today: NaiveDate, holidays: &[Holiday],) -> Result<(), Box<dyn std::error::Error>> {
-    // read current cache
-    let mut full_cache: FullCache = if let Ok(cache_content) = fs::read_to_string(CACHE_FILE) {
-        serde_json::from_str(&cache_content).unwrap_or_else(|_| FullCache {
-            date: today.to_string(),
-            data: Vec::new(),
+// Public holidays come first, then bank holidays, school holidays and all other types
+fn type_rank(holiday: &Holiday) -> usize {
+    holiday
+        .types
+        .iter()
+        .map(|holiday_type| match holiday_type.as_str() {
+            "Public" => 0,
+            "Bank" => 1,
+            "School" => 2,
+            _ => 3,
         })
-    } else {
-        FullCache {
-            date: today.to_string(),
-            data: Vec::new(),
-        }
-    };
+        .min()
+        .unwrap_or(3)
+}
 
-    // cache check for same day and country code
-    if full_cache.data.iter().any(|data| {
-        data.country_code == country_code && data.date == today.to_string()
-    }) {
+// Settings read from CONFIG_FILE, e.g. `ignore = ["Reformation Day", "DE:Epiphany"]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    ignore: Vec<String>,
+    cache_ttl: Option<String>, // Like --cache-ttl, e.g. "30d"
+    #[serde(default)]
+    compress_cache: bool, // Like --compress-cache
+    cache_format: Option<CacheFormat>, // Like --cache-format
+    max_cache_entries: Option<usize>, // Like --max-cache-entries
+    #[serde(default)]
+    providers: Vec<Provider>, // Tried in order when one is down, unless --provider is given
+    #[serde(default)]
+    api_urls: BTreeMap<String, String>, // Base URLs by provider name, for mirrors
+    calendarific_api_key: Option<String>, // The CALENDARIFIC_API_KEY environment variable takes precedence
+    holidayapi_api_key: Option<String>,   // The HOLIDAYAPI_API_KEY environment variable takes precedence
+}
+
+// A missing config file is the same as an empty one
+fn read_config_file() -> Result<ConfigFile, Box<dyn std::error::Error>> {
+    match fs::read_to_string(CONFIG_FILE) {
+        Ok(content) => Ok(toml::from_str(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ConfigFile::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn is_past(holiday: &Holiday, today: NaiveDate) -> bool {
+    holiday.date < today
+}
+
+
+// Adds the entries to the cache file in a single write
+fn write_cache(today: NaiveDate, entries: Vec<CachedData>) -> Result<(), Box<dyn std::error::Error>> {
+    if NO_CACHE.load(Ordering::Relaxed) {
         return Ok(());
     }
+    cache::update_cache(today, |full_cache| add_cache_entries(full_cache, today, entries))
+}
+
+fn add_cache_entries(full_cache: &mut FullCache, today: NaiveDate, entries: Vec<CachedData>) {
+    full_cache.date = today;
+    for entry in entries {
+        // An expired entry of the same provider, country and year is replaced
+        full_cache.data.insert(entry.key(), entry);
+    }
+}
 
-    // create new cache data
-    let new_cached_data = CachedData {
-        country_code: country_code.to_string(),
-        date: today.to_string(),
-        holidays: holidays.to_vec(),
+// Writes to a temporary file next to `path` and renames it, so a failed write never replaces an existing file
+fn write_file_atomically(
+    path: &Path,
+    create_dirs: bool,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     };
+    if !directory.exists() {
+        if create_dirs {
+            fs::create_dir_all(directory).inspect_err(|err| {
+                handle_file_error(err, &directory.display().to_string());
+            })?;
+        } else {
+            eprintln!("Error: The directory '{}' does not exist. Use '--mkdir' to create it.", directory.display());
+            exit_with_error();
+        }
+    }
 
-    // adding new data without deleting old data
-    full_cache.data.push(new_cached_data);
-
-    // Update cache file
-    let cache_content = serde_json::to_string(&full_cache)?;
-     fs::write(CACHE_FILE, cache_content).map_err(|err| {
-            handle_file_error(&err, CACHE_FILE);
-            err
-        })?;
+    if let Err(err) = try_write_file_atomically(path, write) {
+        eprintln!("Error: Could not write '{}': {}", path.display(), err);
+        exit_with_error();
+    }
 
     Ok(())
 }
 
-fn reset_cache_if_needed(today: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
-    if let Ok(cache_content) = fs::read_to_string(CACHE_FILE) {
-        if let Ok(full_cache) = serde_json::from_str::<FullCache>(&cache_content) {
-            // Check cache date
-            if full_cache.date != today.to_string() {
-                println!("New day detected. Resetting cache...");
-                // It is a new day so clean cache
-                let new_cache = FullCache {
-                    date: today.to_string(),
-                    data: Vec::new(),
-                };
-                let cache_content = serde_json::to_string(&new_cache)?;
-                fs::write(CACHE_FILE, cache_content).map_err(|err| {
-                    handle_file_error(&err, CACHE_FILE);
-                    err
-                })?;
-            }
-        }
-    } else {
-        // If there is no cache file, create a new one
-        let new_cache = FullCache {
-            date: today.to_string(),
-            data: Vec::new(),
-        };
-        let cache_content = serde_json::to_string(&new_cache)?;
-           fs::write(CACHE_FILE, cache_content).map_err(|err| {
-            handle_file_error(&err, CACHE_FILE);
-            err
-        })?;
+// Like write_file_atomically, but the directory has to exist and a failed write is returned to the caller
+fn try_write_file_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or("output path has no file name")?;
+    let temp_path = directory.join(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = fs::File::create(&temp_path)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+// Entries expire one by one after the cache TTL, so the cache file itself is never reset
+fn create_cache_if_missing(today: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    if !NO_CACHE.load(Ordering::Relaxed) && fs::metadata(cache_file()).is_err() {
+        // If there is no cache file, create a new one. Another process may have created it in the meantime.
+        cache::update_cache(today, |_| {})?;
     }
 
     Ok(())
 }
 
-fn handle_http_error(status: reqwest::StatusCode) {
+fn handle_http_error(status: reqwest::StatusCode) -> ! {
     match status.as_u16() {
         400 => {
             eprintln!("Error: Bad Request.");
@@ -262,7 +3432,7 @@ fn handle_http_error(status: reqwest::StatusCode) {
             eprintln!("Error: Unexpected HTTP status: {}", status);
         }
     }
-    std::process::exit(1);
+    exit_with_error();
 }
 
 fn handle_file_error(err: &std::io::Error, file_name: &str) {
@@ -277,23 +3447,628 @@ fn handle_file_error(err: &std::io::Error, file_name: &str) {
             eprintln!("Error: An unexpected error occurred with '{}': {}", file_name, err);
         }
     }
-    std::process::exit(1);
+    exit_with_error();
 }
 
-fn print_holiday(holiday: &Holiday) {
-    println!(
-        "Date: {}, Name: {}, Counties: {}, Types: {}",
-        holiday.date,
-        holiday.name,
-        match &holiday.counties {
-            Some(counties) => counties.join(", "),
-            None => "National".to_string(),
-        },
-        if holiday.types.len() == 1 {
-            holiday.types[0].clone()
-        } else {
-            holiday.types.join(", ")
-        }
-    );
+// Messages that are not part of the data go to stderr so stdout can be piped
+fn print_info(message: &str) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
+}
+
+fn print_verbose(message: &str) {
+    if VERBOSE.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server::{Response, TestServer};
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn holiday(date: NaiveDate, name: &str, types: &[&str]) -> Holiday {
+        Holiday {
+            date,
+            local_name: None,
+            name: name.to_string(),
+            counties: None,
+            global: Some(true),
+            fixed: Some(true),
+            types: types.iter().map(|holiday_type| holiday_type.to_string()).collect(),
+            observed: None,
+        }
+    }
+
+    fn german_holidays() -> Vec<Holiday> {
+        vec![
+            holiday(date(2026, 1, 1), "New Year's Day", &["Public"]),
+            holiday(date(2026, 4, 3), "Good Friday", &["Public"]),
+            holiday(date(2026, 5, 1), "Labour Day", &["Public"]),
+            holiday(date(2026, 10, 3), "German Unity Day", &["Public"]),
+            holiday(date(2026, 12, 25), "Christmas Day", &["Public"]),
+            holiday(date(2026, 12, 26), "St. Stephen's Day", &["Public"]),
+        ]
+    }
+
+    // The defaults of the command line, with a fixed day instead of the clock
+    fn list_config(today: NaiveDate) -> ListConfig {
+        ListConfig {
+            country_code: "DE".to_string(),
+            year: today.year(),
+            today,
+            period: Period::Upcoming,
+            mark_past: false,
+            all: false,
+            limit: 5,
+            slice: None,
+            output: OutputFormat::Text,
+            output_file: None,
+            create_dirs: false,
+            counties_width: 30,
+            line_template: None,
+            fields: None,
+            report_template: None,
+            color: false,
+            header: false,
+            full_page: false,
+            pretty: false,
+            org_category: None,
+            long_weekdays: false,
+            group_by: None,
+            show_empty_months: false,
+            language: Language::English,
+            bridge_days: false,
+            flags: false,
+            sort: SortKey::Date,
+            reverse: false,
+            count: false,
+            fail_if_none: false,
+            counties: Vec::new(),
+            types: Vec::new(),
+            excluded_types: Vec::new(),
+            national_only: false,
+            fixed: None,
+            day_filter: None,
+            search: Vec::new(),
+            name_regex: None,
+            invert_match: false,
+            ignored: Vec::new(),
+            show_ignored: false,
+            envelope: true,
+            source: DataSource::Cache,
+            provider: None,
+            fetched_at: None,
+        }
+    }
+
+    // Each test lists fewer holidays than are left in the year, so the next year is never requested
+    async fn selected_dates(config: &ListConfig) -> Vec<NaiveDate> {
+        select_holidays(&german_holidays(), config).await.unwrap().iter().map(|holiday| holiday.date).collect()
+    }
+
+    fn nager_options(base_url: &str) -> ProviderOptions {
+        ProviderOptions {
+            provider: Provider::Nager,
+            kind: HolidayKind::Public,
+            subdivision: None,
+            base_url: base_url.to_string(),
+            api_key: None,
+        }
+    }
+
+    // The providers are set once for the whole test binary: Nager, then OpenHolidays, both on this server.
+    // DE has a holiday, XK answers 204, FR an empty list and AT an outage on Nager. Codes starting with Q answer
+    // like DE after SLOW_ANSWER.
+    const SLOW_ANSWER: std::time::Duration = std::time::Duration::from_millis(200);
+
+    fn test_providers() -> &'static TestServer {
+        static SERVER: OnceLock<TestServer> = OnceLock::new();
+        let server = SERVER.get_or_init(|| {
+            TestServer::start(|request| {
+                let path = request.path.as_str();
+                if path.starts_with("/PublicHolidays?countryIsoCode=AT&") {
+                    return Response::json(
+                        r#"[{"startDate":"2026-10-26","endDate":"2026-10-26","type":"Public","name":[{"language":"EN","text":"National Day"}],"nationwide":true}]"#,
+                    );
+                }
+                let country_code = path.rsplit('/').next().unwrap_or_default();
+                if country_code.starts_with('Q') {
+                    std::thread::sleep(SLOW_ANSWER);
+                }
+                match country_code {
+                    "DE" | "QA" | "QB" | "QC" | "QD" | "QE" | "QF" | "QG" | "QH" => Response::json(
+                        r#"[{"date":"2026-10-03","localName":"Tag der Deutschen Einheit","name":"German Unity Day","counties":null,"global":true,"fixed":true,"types":["Public"]}]"#,
+                    ),
+                    "XK" => Response::status(204),
+                    "FR" => Response::json("[]"),
+                    _ => Response::status(503),
+                }
+            })
+        });
+        PROVIDERS.get_or_init(|| {
+            let openholidays = ProviderOptions { provider: Provider::Openholidays, ..nager_options(&server.url) };
+            vec![nager_options(&server.url), openholidays]
+        });
+        server
+    }
+
+    // The cache lock is a std mutex, so it is held around the runtime and not across an await
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+    }
+
+    fn cached_countries() -> Vec<(String, String)> {
+        cache::load_cache()
+            .unwrap()
+            .map(|full_cache| full_cache.data.sorted().iter().map(|entry| (entry.provider.clone(), entry.country_code.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn not_modified_keeps_the_cached_holidays() {
+        let server = TestServer::start(|request| match request.header("if-none-match") {
+            Some("\"v1\"") => Response::status(304),
+            _ => Response::json("[]"),
+        });
+        let mut cached = cache::test_entry("DE", 2026, "2026-01-02T03:04:05Z");
+        cached.validators = Validators { etag: Some("\"v1\"".to_string()), last_modified: Some("Fri, 02 Jan 2026 03:04:05 GMT".to_string()) };
+
+        let data = fetch_holidays(&nager_options(&server.url), "DE", 2026, today(), Some(&cached)).await.unwrap();
+        assert_eq!(server.paths(), ["/api/v3/publicholidays/2026/DE"]);
+        assert_eq!(server.requests()[0].header("if-modified-since"), Some("Fri, 02 Jan 2026 03:04:05 GMT"));
+        assert_eq!(data.source, DataSource::Api);
+        assert_eq!(serde_json::to_value(&data.holidays).unwrap(), serde_json::to_value(&cached.holidays).unwrap());
+        assert_ne!(data.fetched_at, cached.fetched_at);
+        // The 304 sent no validators, so the cached ones are kept
+        assert_eq!(data.validators.etag, cached.validators.etag);
+        assert_eq!(data.validators.last_modified, cached.validators.last_modified);
+    }
+
+    #[tokio::test]
+    async fn not_modified_updates_the_validators() {
+        let server = TestServer::start(|request| match request.header("if-none-match") {
+            Some("\"v1\"") => Response::status(304).header("ETag", "\"v2\""),
+            _ => Response::json("[]"),
+        });
+        let mut cached = cache::test_entry("DE", 2026, "2026-01-02T03:04:05Z");
+        cached.validators.etag = Some("\"v1\"".to_string());
+
+        let data = fetch_holidays(&nager_options(&server.url), "DE", 2026, today(), Some(&cached)).await.unwrap();
+        assert_eq!(data.holidays.len(), cached.holidays.len());
+        assert_eq!(data.holidays[0].date, cached.holidays[0].date);
+        assert_eq!(data.validators.etag.as_deref(), Some("\"v2\""));
+    }
+
+    #[tokio::test]
+    async fn reverse_flips_the_next_holidays_after_the_limit() {
+        let mut config = list_config(date(2026, 4, 1));
+        config.limit = 2;
+        assert_eq!(selected_dates(&config).await, [date(2026, 4, 3), date(2026, 5, 1)]);
+        // Still the next two holidays, not the last two of the year
+        config.reverse = true;
+        assert_eq!(selected_dates(&config).await, [date(2026, 5, 1), date(2026, 4, 3)]);
+    }
+
+    #[tokio::test]
+    async fn reverse_lists_past_holidays_oldest_first() {
+        let mut config = list_config(date(2026, 6, 1));
+        config.period = Period::Past;
+        config.limit = 2;
+        assert_eq!(selected_dates(&config).await, [date(2026, 5, 1), date(2026, 4, 3)]);
+        config.reverse = true;
+        assert_eq!(selected_dates(&config).await, [date(2026, 4, 3), date(2026, 5, 1)]);
+    }
+
+    #[tokio::test]
+    async fn reverse_lists_the_end_of_the_year_first() {
+        let mut config = list_config(date(2026, 6, 1));
+        config.period = Period::WholeYear;
+        config.all = true;
+        config.reverse = true;
+        let dates = selected_dates(&config).await;
+        assert_eq!(dates.len(), 6);
+        assert_eq!(dates.first(), Some(&date(2026, 12, 26)));
+        assert_eq!(dates.last(), Some(&date(2026, 1, 1)));
+    }
+
+    #[tokio::test]
+    async fn todays_holiday_is_listed_and_marked() {
+        let mut config = list_config(date(2026, 12, 25));
+        config.limit = 2;
+        let selected = select_holidays(&german_holidays(), &config).await.unwrap();
+        assert_eq!(selected.iter().map(|holiday| holiday.date).collect::<Vec<_>>(), [date(2026, 12, 25), date(2026, 12, 26)]);
+
+        let mut out = Vec::new();
+        output::write_holidays(&mut out, &selected, &config).unwrap();
+        let lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(str::to_string).collect();
+        assert!(lines[0].contains("Christmas Day") && lines[0].ends_with("(today)"), "{}", lines[0]);
+        assert!(lines[1].ends_with("(tomorrow)"), "{}", lines[1]);
+    }
+
+    #[tokio::test]
+    async fn todays_holiday_is_upcoming_and_not_past() {
+        let mut config = list_config(date(2026, 12, 26));
+        config.limit = 1;
+        assert_eq!(selected_dates(&config).await, [date(2026, 12, 26)]);
+        config.period = Period::Past;
+        assert_eq!(selected_dates(&config).await, [date(2026, 12, 25)]);
+    }
+
+    #[test]
+    fn excluded_types_only_drop_holidays_entirely_within_them() {
+        let mut config = list_config(today());
+        config.excluded_types = vec!["school".to_string()];
+        assert!(!matches_filters(&holiday(today(), "Autumn Break", &["School"]), &config));
+        // Both public and a school holiday
+        assert!(matches_filters(&holiday(today(), "Reformation Day", &["Public", "School"]), &config));
+        assert!(matches_filters(&holiday(today(), "German Unity Day", &["Public"]), &config));
+
+        config.excluded_types = vec!["school".to_string(), "optional".to_string()];
+        assert!(!matches_filters(&holiday(today(), "Carnival", &["School", "Optional"]), &config));
+        assert!(matches_filters(&holiday(today(), "Carnival", &["School", "Optional", "Bank"]), &config));
+    }
+
+    #[test]
+    fn excluded_types_are_compared_in_lower_case() {
+        let mut config = list_config(today());
+        config.excluded_types = vec!["bank".to_string()];
+        assert!(!matches_filters(&holiday(today(), "Summer Bank Holiday", &["Bank"]), &config));
+    }
+
+    #[test]
+    fn excluded_types_combine_with_the_chosen_types() {
+        let mut config = list_config(today());
+        config.types = vec!["public".to_string()];
+        config.excluded_types = vec!["school".to_string()];
+        assert!(matches_filters(&holiday(today(), "Reformation Day", &["Public", "School"]), &config));
+        assert!(!matches_filters(&holiday(today(), "Autumn Break", &["School"]), &config));
+        assert!(!matches_filters(&holiday(today(), "Summer Bank Holiday", &["Bank"]), &config));
+    }
+
+    #[tokio::test]
+    async fn last_keeps_the_end_of_the_list_in_date_order() {
+        let mut config = list_config(date(2026, 4, 1));
+        config.period = Period::WholeYear;
+        config.slice = Some(Slice::Last(3));
+        assert_eq!(selected_dates(&config).await, [date(2026, 10, 3), date(2026, 12, 25), date(2026, 12, 26)]);
+        config.reverse = true;
+        assert_eq!(selected_dates(&config).await, [date(2026, 12, 26), date(2026, 12, 25), date(2026, 10, 3)]);
+    }
+
+    #[tokio::test]
+    async fn first_and_last_replace_the_limit() {
+        let mut config = list_config(date(2026, 4, 1));
+        config.limit = 1;
+        config.slice = Some(Slice::First(2));
+        assert_eq!(selected_dates(&config).await, [date(2026, 4, 3), date(2026, 5, 1)]);
+        // The last of the upcoming holidays, not of the ones within the limit
+        config.slice = Some(Slice::Last(2));
+        assert_eq!(selected_dates(&config).await, [date(2026, 12, 25), date(2026, 12, 26)]);
+        config.slice = Some(Slice::Last(10));
+        assert_eq!(selected_dates(&config).await.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn first_slices_the_filtered_list() {
+        let mut config = list_config(date(2026, 1, 1));
+        config.period = Period::WholeYear;
+        config.search = vec!["labour".to_string(), "christmas".to_string(), "stephen".to_string()];
+        config.slice = Some(Slice::First(2));
+        assert_eq!(selected_dates(&config).await, [date(2026, 5, 1), date(2026, 12, 25)]);
+    }
+
+    #[tokio::test]
+    async fn within_crosses_into_the_next_year() {
+        let today = date(2026, 12, 25);
+        let (from, to) = within_bounds(today, 14);
+        assert_eq!((from, to), (date(2026, 12, 26), date(2027, 1, 8)));
+        // Both years are requested
+        assert_eq!((from.year(), to.year()), (2026, 2027));
+
+        let mut holidays = german_holidays();
+        holidays.push(holiday(date(2027, 1, 1), "New Year's Day", &["Public"]));
+        holidays.push(holiday(date(2027, 1, 9), "Not a Holiday", &["Public"]));
+        let mut config = list_config(today);
+        config.period = Period::Range(from, to);
+        config.all = true;
+        let selected = select_holidays(&holidays, &config).await.unwrap();
+        // Today is not within the next days
+        assert_eq!(selected.iter().map(|holiday| holiday.date).collect::<Vec<_>>(), [date(2026, 12, 26), date(2027, 1, 1)]);
+    }
+
+    #[test]
+    fn within_includes_the_last_day() {
+        let today = date(2026, 12, 25);
+        let mut config = list_config(today);
+        let (from, to) = within_bounds(today, 7);
+        config.period = Period::Range(from, to);
+        assert!(in_period(&holiday(date(2027, 1, 1), "New Year's Day", &["Public"]), &config));
+        let (from, to) = within_bounds(today, 6);
+        config.period = Period::Range(from, to);
+        assert!(!in_period(&holiday(date(2027, 1, 1), "New Year's Day", &["Public"]), &config));
+        let (from, to) = within_bounds(today, 1);
+        config.period = Period::Range(from, to);
+        assert!(in_period(&holiday(date(2026, 12, 26), "St. Stephen's Day", &["Public"]), &config));
+        assert!(!in_period(&holiday(today, "Christmas Day", &["Public"]), &config));
+    }
+
+    #[test]
+    fn json_output_matches_the_schema() {
+        let mut schema = Vec::new();
+        output::write_schema(&mut schema, SchemaFormat::Json).unwrap();
+        let schema: serde_json::Value = serde_json::from_slice(&schema).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+
+        let mut holidays = german_holidays();
+        holidays[1].fixed = Some(false);
+        holidays[2].local_name = Some("Tag der Arbeit".to_string());
+        holidays[3].counties = Some(vec!["DE-BY".to_string(), "DE-BW".to_string()]);
+        holidays[3].global = Some(false);
+        holidays[4].observed = Some(date(2026, 12, 28));
+        holidays[5].fixed = None;
+        let mut config = list_config(today());
+        config.period = Period::WholeYear;
+        config.all = true;
+        config.output = OutputFormat::Json;
+        config.ignored = vec![IgnoreRule::parse("DE:Good Friday")];
+        config.show_ignored = true;
+        for (source, provider, fetched_at) in
+            [(DataSource::Api, Some("nager"), Some("2026-10-14T08:00:00Z")), (DataSource::File, None, None)]
+        {
+            config.source = source;
+            config.provider = provider.map(str::to_string);
+            config.fetched_at = fetched_at.map(str::to_string);
+            let mut out = Vec::new();
+            output::write_holidays(&mut out, &holidays, &config).unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            assert_eq!(json["holidays"].as_array().map(Vec::len), Some(holidays.len()));
+            assert_eq!(json["holidays"][1]["ignored"], true);
+            let errors: Vec<String> = validator.iter_errors(&json).map(|error| error.to_string()).collect();
+            assert!(errors.is_empty(), "{:?} in {}", errors, json);
+        }
+    }
+
+    #[test]
+    fn toml_output_round_trips() {
+        let names = ["Tag der \"Deutschen\" Einheit", "C:\\Weihnachten # 1", "Ostern\nMontag", "Fête nationale 🇫🇷", "'Single' quotes"];
+        let mut holidays: Vec<Holiday> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| holiday(date(2026, 3, index as u32 + 1), name, &["Public", "Bank"]))
+            .collect();
+        holidays[0].counties = Some(vec!["DE-BY".to_string(), "DE-BW".to_string()]);
+        let mut config = list_config(today());
+        config.period = Period::WholeYear;
+        config.all = true;
+        config.output = OutputFormat::Toml;
+        let mut out = Vec::new();
+        output::write_holidays(&mut out, &holidays, &config).unwrap();
+
+        let document: toml::Table = toml::from_str(std::str::from_utf8(&out).unwrap()).unwrap();
+        let listed = document["country"]["DE"]["holiday"].as_array().unwrap();
+        assert_eq!(listed.len(), names.len());
+        for (listed, holiday) in listed.iter().zip(&holidays) {
+            assert_eq!(listed["name"].as_str(), Some(holiday.name.as_str()));
+            assert_eq!(listed["date"].as_str(), Some(holiday.date.to_string().as_str()));
+            assert_eq!(listed["types"], toml::Value::from(vec!["Public", "Bank"]));
+        }
+        assert_eq!(listed[0]["counties"], toml::Value::from(vec!["DE-BY", "DE-BW"]));
+        // National holidays have no counties key instead of a null
+        assert!(listed[1].get("counties").is_none());
+    }
+
+    #[test]
+    fn pipes_get_tab_separated_lines_unless_a_format_is_chosen() {
+        let format = |arguments: &[&str], stdout_is_terminal| {
+            let args = Args::try_parse_from(std::iter::once("get_holiday").chain(arguments.iter().copied())).unwrap();
+            output_format(&args, stdout_is_terminal)
+        };
+        assert_eq!(format(&["DE"], true), OutputFormat::Text);
+        assert_eq!(format(&["DE"], false), OutputFormat::Tsv);
+        // A file is no terminal either
+        assert_eq!(format(&["DE", "--output-file", "holidays.txt"], true), OutputFormat::Tsv);
+        assert_eq!(format(&["DE", "--output", "table"], false), OutputFormat::Table);
+        assert_eq!(format(&["DE", "--output", "text"], false), OutputFormat::Text);
+        assert_eq!(format(&["DE", "--group-by", "month"], false), OutputFormat::Text);
+        assert_eq!(format(&["DE", "--color", "always"], false), OutputFormat::Text);
+    }
+
+    #[test]
+    fn terminal_and_pipe_render_differently() {
+        let mut config = list_config(date(2026, 12, 1));
+        config.limit = 2;
+        let holidays = &german_holidays()[4..];
+        let render = |config: &ListConfig| {
+            let mut out = Vec::new();
+            output::write_holidays(&mut out, holidays, config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        config.output = OutputFormat::Text;
+        let text = render(&config);
+        assert!(text.starts_with("Date: 2026-12-25 (Fri), Name: Christmas Day, Counties: "), "{}", text);
+
+        config.output = OutputFormat::Tsv;
+        let tsv = render(&config);
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", tsv);
+        assert!(lines[0].starts_with("2026-12-25\tChristmas Day\t"), "{}", lines[0]);
+        // No padding around the fields
+        assert!(lines.iter().flat_map(|line| line.split('\t')).all(|field| field.trim() == field), "{}", tsv);
+    }
+
+    #[test]
+    fn empty_answers_are_not_cached() {
+        let _cache = cache::use_test_cache();
+        let server = test_providers();
+        block_on(async {
+            for country_code in ["XK", "FR", "XK"] {
+                let data = get_holidays(country_code, 2026, today()).await.unwrap();
+                assert!(data.holidays.is_empty(), "{}", country_code);
+                assert_eq!(data.source, DataSource::Api);
+            }
+            let data = get_holidays("DE", 2026, today()).await.unwrap();
+            assert_eq!(data.holidays.len(), 1);
+        });
+        assert_eq!(cached_countries(), [("nager".to_string(), "DE".to_string())]);
+        // XK was requested again, because nothing was cached for it
+        let paths = server.paths();
+        assert_eq!(paths.iter().filter(|path| path.as_str() == "/api/v3/publicholidays/2026/XK").count(), 2, "{:?}", paths);
+    }
+
+    #[test]
+    fn years_outside_the_data_are_rejected() {
+        assert_eq!(check_year(MIN_YEAR, 2026, false), Ok(()));
+        assert_eq!(check_year(2026 + MAX_YEARS_AHEAD, 2026, false), Ok(()));
+        let err = check_year(1950, 2026, false).unwrap_err();
+        assert!(err.contains(&format!("from {} to {}", MIN_YEAR, 2026 + MAX_YEARS_AHEAD)), "{}", err);
+        assert!(err.contains("--allow-any-year"), "{}", err);
+        assert!(check_year(MIN_YEAR - 1, 2026, false).is_err());
+        assert!(check_year(2026 + MAX_YEARS_AHEAD + 1, 2026, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn allow_any_year_sends_the_request_anyway() {
+        let server = TestServer::start(|_| Response::json("[]"));
+        let options = nager_options(&server.url);
+        for (flag, requested) in [(None, false), (Some("--allow-any-year"), true)] {
+            let args = Args::try_parse_from(["get_holiday", "DE", "--year", "1950"].into_iter().chain(flag)).unwrap();
+            let year = args.year.unwrap();
+            // The same check the commands run before fetching
+            if check_year(year, 2026, args.allow_any_year).is_ok() {
+                fetch_holidays(&options, "DE", year, today(), None).await.unwrap();
+            }
+            let paths = server.paths();
+            assert_eq!(paths.contains(&"/api/v3/publicholidays/1950/DE".to_string()), requested, "{:?}", paths);
+        }
+        assert_eq!(server.paths(), ["/api/v3/publicholidays/1950/DE"]);
+        assert_eq!(check_year(2200, 2026, true), Ok(()));
+    }
+
+    #[test]
+    fn outages_fall_back_to_the_next_provider() {
+        let _cache = cache::use_test_cache();
+        let server = test_providers();
+        let data = block_on(get_holidays("AT", 2026, today())).unwrap();
+        assert_eq!(data.source, DataSource::Api);
+        assert_eq!(data.provider.as_deref(), Some("openholidays/public"));
+        assert_eq!(data.holidays.iter().map(|holiday| holiday.date).collect::<Vec<_>>(), [date(2026, 10, 26)]);
+        // Nager was retried before OpenHolidays was asked
+        let paths: Vec<String> = server.paths().into_iter().filter(|path| path.contains("AT")).collect();
+        let nager_requests = 1 + FETCH_RETRIES as usize;
+        assert_eq!(paths.len(), nager_requests + 1, "{:?}", paths);
+        assert!(paths[..nager_requests].iter().all(|path| path == "/api/v3/publicholidays/2026/AT"), "{:?}", paths);
+        assert!(paths[nager_requests].starts_with("/PublicHolidays?countryIsoCode=AT&"), "{:?}", paths);
+        // Cached under the provider that answered
+        assert_eq!(cached_countries(), [("openholidays/public".to_string(), "AT".to_string())]);
+    }
+
+    #[test]
+    fn provider_option_disables_the_fallback() {
+        let config_file = ConfigFile { providers: vec![Provider::Nager, Provider::Openholidays], ..ConfigFile::default() };
+        let providers = |arguments: &[&str]| -> Vec<Provider> {
+            let args = Args::try_parse_from(std::iter::once("get_holiday").chain(arguments.iter().copied())).unwrap();
+            provider_list(&args, &config_file).iter().map(|options| options.provider).collect()
+        };
+        assert_eq!(providers(&["DE"]), [Provider::Nager, Provider::Openholidays]);
+        assert_eq!(providers(&["DE", "--provider", "openholidays"]), [Provider::Openholidays]);
+    }
+
+    fn cached_entry(country_code: &str, year: i32) -> Option<CachedData> {
+        let full_cache = cache::load_cache().unwrap()?;
+        full_cache.data.values().find(|entry| entry.country_code == country_code && entry.year == year).cloned()
+    }
+
+    #[test]
+    fn cache_hits_do_not_wait_for_the_lock() {
+        let _cache = cache::use_test_cache();
+        test_providers();
+        let fetched_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        write_cache(today(), vec![cache::test_entry("DE", 2026, &fetched_at)]).unwrap();
+
+        let lock = cache::lock_cache().unwrap();
+        let started = Instant::now();
+        assert!(check_cache("DE", 2026).unwrap().is_some());
+        assert!(started.elapsed() < std::time::Duration::from_secs(1), "{:?}", started.elapsed());
+        // The access time is not written while another process holds the lock
+        assert_eq!(cached_entry("DE", 2026).unwrap().last_accessed, None);
+        drop(lock);
+
+        assert!(check_cache("DE", 2026).unwrap().is_some());
+        assert!(cached_entry("DE", 2026).unwrap().last_accessed.is_some());
+    }
+
+    #[test]
+    fn prompt_uses_expired_entries_without_the_lock() {
+        let _cache = cache::use_test_cache();
+        test_providers();
+        assert_eq!(prompt_holidays("DE", 2026).map(|holidays| holidays.len()), None);
+        write_cache(today(), vec![cache::test_entry("DE", 2026, "2020-01-01T00:00:00Z")]).unwrap();
+        let modified = || fs::metadata(cache_file()).unwrap().modified().unwrap();
+        let written = modified();
+        cache::forget_loaded_cache();
+
+        let _lock = cache::lock_cache().unwrap();
+        let started = Instant::now();
+        let holidays = prompt_holidays("DE", 2026).unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(1), "{:?}", started.elapsed());
+        assert_eq!(holidays.iter().map(|holiday| holiday.date).collect::<Vec<_>>(), [date(2026, 12, 25)]);
+        assert_eq!(prompt_holidays("DE", 2027).map(|holidays| holidays.len()), None);
+        assert_eq!(modified(), written);
+        assert_eq!(cached_entry("DE", 2026).unwrap().last_accessed, None);
+    }
+
+    #[test]
+    fn concurrent_requests_take_less_time() {
+        let _cache = cache::use_test_cache();
+        let server = test_providers();
+        let country_codes: Vec<String> = ["QA", "QB", "QC", "QD", "QE", "QF", "QG", "QH"].map(str::to_string).to_vec();
+        // A different year each time, so nothing comes from the cache
+        let fetch_time = |year, concurrency| {
+            let started = Instant::now();
+            let (country_data, failures) =
+                block_on(get_countries_holidays(&country_codes, year, year, today(), concurrency, true)).unwrap();
+            assert_eq!(country_data.len(), country_codes.len());
+            assert!(failures.is_empty());
+            started.elapsed()
+        };
+        let one_at_a_time = fetch_time(2026, 1);
+        let four_at_a_time = fetch_time(2027, 4);
+        assert!(one_at_a_time >= SLOW_ANSWER * 8, "{:?}", one_at_a_time);
+        assert!(four_at_a_time < one_at_a_time / 2, "{:?} with 4 requests at a time, {:?} with 1", four_at_a_time, one_at_a_time);
+        assert_eq!(server.paths().iter().filter(|path| path.contains("/Q")).count(), 16);
+    }
+
+    #[test]
+    fn stdin_and_country_arguments_conflict() {
+        let err = Args::try_parse_from(["get_holiday", "DE", "--stdin"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(Args::try_parse_from(["get_holiday", "--stdin"]).unwrap().stdin);
+    }
+
+    #[test]
+    fn country_code_lines_skip_blanks_comments_and_duplicates() {
+        let available: Vec<AvailableCountry> = ["DE", "AT", "FR"]
+            .map(|country_code| AvailableCountry { country_code: country_code.to_string(), name: String::new() })
+            .to_vec();
+        let lines: Vec<String> = ["de", "", "  # offices", "AT # Vienna", "XX", " de ", "fr", "Q1#"].map(str::to_string).to_vec();
+        let parsed = parse_country_code_lines(&lines, &available);
+        assert_eq!(parsed.country_codes, ["DE", "AT", "FR"]);
+        assert_eq!(parsed.invalid, [(5, "XX".to_string()), (8, "Q1".to_string())]);
+        assert_eq!(parsed.codes_given, 6);
+
+        let parsed = parse_country_code_lines(&["# only a comment".to_string(), "   ".to_string()], &available);
+        assert!(parsed.country_codes.is_empty() && parsed.invalid.is_empty());
+        assert_eq!(parsed.codes_given, 0);
+    }
+}
@@ -0,0 +1,1154 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, SecondsFormat, Utc, Weekday};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::bridge;
+use crate::fields::Field;
+use crate::{is_ignored, is_past, CommandFormat, DataSource, GroupBy, Holiday, Language, ListConfig, OutputFormat, SchemaFormat, WeekStart, WorldwideHoliday};
+
+// A holiday together with the values derived from its date, computed in one place for every output format
+#[derive(Serialize, JsonSchema, Debug)]
+struct HolidayRecord<'a> {
+    #[serde(flatten)]
+    holiday: &'a Holiday,
+    weekday: String,
+    #[serde(skip)]
+    weekend: bool,
+    #[serde(skip)]
+    display_name: String, // Name in the language chosen with --lang
+    // Only listed with --show-ignored. The default keeps it optional in the schema.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ignored: bool,
+}
+
+fn holiday_records<'a>(holidays: &'a [Holiday], config: &ListConfig) -> Vec<HolidayRecord<'a>> {
+    holidays
+        .iter()
+        .map(|holiday| {
+            HolidayRecord {
+                holiday,
+                weekday: weekday_name(holiday.date, config.long_weekdays),
+                weekend: matches!(holiday.date.weekday(), Weekday::Sat | Weekday::Sun),
+                display_name: holiday.display_name(config.language),
+                ignored: is_ignored(holiday, config),
+            }
+        })
+        .collect()
+}
+
+pub fn weekday_name(date: NaiveDate, long: bool) -> String {
+    if long {
+        date.format("%A").to_string()
+    } else {
+        date.format("%a").to_string()
+    }
+}
+
+// Wraps the JSON and YAML output, so it is visible whether the data came from the cache and how old it is
+#[derive(Serialize, JsonSchema)]
+struct Envelope<'r, T: Serialize> {
+    country: &'r str,
+    year: i32,
+    source: DataSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<&'r str>, // Missing for --holidays-file
+    fetched_at: Option<&'r str>,
+    generated_at: String,
+    holidays: T,
+}
+
+fn envelope<T: Serialize>(records: T, config: &ListConfig) -> Envelope<'_, T> {
+    Envelope {
+        country: &config.country_code,
+        year: config.year,
+        source: config.source,
+        provider: config.provider.as_deref(),
+        fetched_at: config.fetched_at.as_deref(),
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        holidays: records,
+    }
+}
+
+// Generated from the types that are serialized, so the schema always matches the JSON output
+pub fn write_schema(out: &mut dyn Write, format: SchemaFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let schema = schemars::schema_for!(Envelope<'static, Vec<HolidayRecord<'static>>>);
+    match format {
+        SchemaFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&schema)?)?,
+        SchemaFormat::Yaml => write!(out, "{}", serde_yaml::to_string(&schema)?)?,
+    }
+    Ok(())
+}
+
+// Columns of the table output and of the CSV and TSV output when no --fields are given
+const TABLE_FIELDS: [Field; 5] = [Field::Date, Field::Weekday, Field::Name, Field::Counties, Field::Types];
+const DELIMITED_FIELDS: [Field; 7] =
+    [Field::Date, Field::Name, Field::Counties, Field::Types, Field::Weekday, Field::LocalName, Field::Fixed];
+
+// The value of a field in the structured output, lists stay arrays and national holidays have no counties
+fn field_value(record: &HolidayRecord, field: Field, config: &ListConfig) -> serde_json::Value {
+    let holiday = record.holiday;
+    match field {
+        Field::Date => serde_json::json!(holiday.date),
+        Field::Name => serde_json::json!(holiday.name),
+        Field::LocalName => serde_json::json!(holiday.local_name),
+        Field::Counties => serde_json::json!(holiday.counties),
+        Field::Types => serde_json::json!(holiday.types),
+        Field::Weekday => serde_json::json!(record.weekday),
+        Field::DaysUntil => serde_json::json!((holiday.date - config.today).num_days()),
+        Field::Country => serde_json::json!(config.country_code),
+        Field::Fixed => serde_json::json!(holiday.fixed),
+    }
+}
+
+// The value of a field as a single cell, lists are joined with `separator`
+fn field_text(record: &HolidayRecord, field: Field, config: &ListConfig, separator: &str) -> String {
+    match field_value(record, field, config) {
+        // Older cache entries don't know whether a holiday is fixed
+        serde_json::Value::Null if field == Field::Fixed => "?".to_string(),
+        serde_json::Value::String(text) => text,
+        serde_json::Value::Array(items) => {
+            items.iter().filter_map(|item| item.as_str()).collect::<Vec<_>>().join(separator)
+        }
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// The value of a field as it is shown to a reader in the text and table output
+fn field_display(record: &HolidayRecord, field: Field, config: &ListConfig) -> String {
+    match field {
+        Field::Name => record.display_name.clone(),
+        Field::Counties => record.holiday.counties_text(),
+        Field::Weekday => weekday_cell(record),
+        _ => field_text(record, field, config, ", "),
+    }
+}
+
+// The selected fields of a holiday as a JSON object, keys keep the order of --fields
+struct FieldRecord(Vec<(&'static str, serde_json::Value)>);
+
+impl Serialize for FieldRecord {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+fn field_records(records: &[HolidayRecord], fields: &[Field], config: &ListConfig) -> Vec<FieldRecord> {
+    records
+        .iter()
+        .map(|record| FieldRecord(fields.iter().map(|field| (field.name(), field_value(record, *field, config))).collect()))
+        .collect()
+}
+
+// The holidays in the JSON output, either whole or only their --fields
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsonRecords<'r, 'a> {
+    Holidays(&'r [HolidayRecord<'a>]),
+    Fields(Vec<FieldRecord>),
+}
+
+fn json_records<'r, 'a>(records: &'r [HolidayRecord<'a>], config: &ListConfig) -> JsonRecords<'r, 'a> {
+    match &config.fields {
+        Some(fields) => JsonRecords::Fields(field_records(records, fields, config)),
+        None => JsonRecords::Holidays(records),
+    }
+}
+
+// The columns of the CSV and TSV output. With several countries the country comes first, so the rows can be told apart.
+fn delimited_fields(countries: &[(&ListConfig, Vec<HolidayRecord>)]) -> Vec<Field> {
+    let mut fields = countries[0].0.fields.clone().unwrap_or(DELIMITED_FIELDS.to_vec());
+    if countries.len() > 1 && !fields.contains(&Field::Country) {
+        fields.insert(0, Field::Country);
+    }
+    fields
+}
+
+const WEEKEND_FOOTNOTE: &str = "* Falls on a weekend";
+const NO_HOLIDAYS_IN_MONTH: &str = "— none —";
+
+// Holidays of one month for --group-by month
+struct MonthGroup<'r, 'a> {
+    month: NaiveDate, // First day of the month
+    records: Vec<&'r HolidayRecord<'a>>,
+}
+
+// Groups follow the order of the listed holidays, but inside a group the holidays are always chronological
+fn month_groups<'r, 'a>(records: &'r [HolidayRecord<'a>], include_empty: bool) -> Vec<MonthGroup<'r, 'a>> {
+    let mut months: Vec<NaiveDate> = Vec::new();
+    for date in records.iter().map(|record| record.holiday.date) {
+        let month = date.with_day(1).expect("first day of month is valid");
+        if !months.contains(&month) {
+            months.push(month);
+        }
+    }
+
+    if include_empty && months.len() > 1 {
+        let descending = months[0] > months[months.len() - 1];
+        let (first, last) = (*months.iter().min().unwrap(), *months.iter().max().unwrap());
+        months = std::iter::successors(Some(first), |month| month.checked_add_months(Months::new(1)))
+            .take_while(|month| *month <= last)
+            .collect();
+        if descending {
+            months.reverse();
+        }
+    }
+
+    months
+        .into_iter()
+        .map(|month| {
+            let mut month_records: Vec<&HolidayRecord> = records
+                .iter()
+                .filter(|record| {
+                    record.holiday.date.year() == month.year() && record.holiday.date.month() == month.month()
+                })
+                .collect();
+            month_records.sort_by_key(|record| record.holiday.date);
+            MonthGroup { month, records: month_records }
+        })
+        .collect()
+}
+
+// Writes the listed holidays in the format selected in the config
+pub fn write_holidays(out: &mut dyn Write, holidays: &[Holiday], config: &ListConfig) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(template) = &config.report_template {
+        write!(out, "{}", template.render(holidays, config)?)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    let records = holiday_records(holidays, config);
+    match config.output {
+        OutputFormat::Text => {
+            let soonest_date = soonest_upcoming_date(&records, config.today);
+            let bridge_hints = if config.bridge_days { bridge_hints(&records, config.today) } else { HashMap::new() };
+            let flag = flag_prefix(config);
+            let write_line = |out: &mut dyn Write, record: &HolidayRecord, indent: &str| -> std::io::Result<()> {
+                let style = row_style(record.holiday, soonest_date, config.color);
+                let line_indent = format!("{}{}", indent, flag);
+                match (&config.line_template, &config.fields) {
+                    (Some(template), _) => writeln!(out, "{}{}", line_indent, template.render(record.holiday, config))?,
+                    (None, Some(fields)) => write_holiday_fields(out, record, fields, config, style, &line_indent)?,
+                    (None, None) => write_holiday(out, record, config, style, &line_indent)?,
+                }
+                if let Some(hint) = bridge_hints.get(&record.holiday.date) {
+                    writeln!(out, "{}    {}", indent, hint)?;
+                }
+                Ok(())
+            };
+            if config.group_by == Some(GroupBy::Month) {
+                for group in month_groups(&records, config.show_empty_months) {
+                    writeln!(out, "{}", group.month.format("%B %Y"))?;
+                    if group.records.is_empty() {
+                        writeln!(out, "  {}", NO_HOLIDAYS_IN_MONTH)?;
+                    }
+                    for record in group.records {
+                        write_line(out, record, "  ")?;
+                    }
+                }
+            } else {
+                for record in &records {
+                    write_line(out, record, "")?;
+                }
+            }
+            let weekday_shown = config.fields.as_ref().is_none_or(|fields| fields.contains(&Field::Weekday));
+            if config.line_template.is_none() && weekday_shown && records.iter().any(|record| record.weekend) {
+                writeln!(out, "{}", WEEKEND_FOOTNOTE)?;
+            }
+        }
+        OutputFormat::Json => {
+            let json = if config.envelope {
+                serde_json::to_string_pretty(&envelope(json_records(&records, config), config))?
+            } else {
+                serde_json::to_string_pretty(&json_records(&records, config))?
+            };
+            writeln!(out, "{}", json)?;
+        }
+        OutputFormat::Yaml if config.envelope => {
+            write!(out, "{}", serde_yaml::to_string(&envelope(&records, config))?)?;
+        }
+        OutputFormat::Csv => write_csv(out, &[(config, records)])?,
+        OutputFormat::Ics => write_ics(out, &records, &config.country_code)?,
+        OutputFormat::Yaml => {
+            // Nested under the country code so documents of several countries can be merged
+            let document = BTreeMap::from([(config.country_code.as_str(), &records)]);
+            write!(out, "{}", serde_yaml::to_string(&document)?)?;
+        }
+        OutputFormat::Table => write_table(out, &records, config)?,
+        OutputFormat::Tsv => write_tsv(out, &[(config, records)])?,
+        OutputFormat::Html => write_html(out, &records, config)?,
+        OutputFormat::Xml => write_xml(out, &[(config, records)])?,
+        OutputFormat::Ndjson => write_ndjson(out, &records, config)?,
+        OutputFormat::Toml => write_toml(out, &records, &config.country_code)?,
+        OutputFormat::Org => write_org(out, &records, config)?,
+        OutputFormat::Remind => write_remind(out, &records, &config.country_code)?,
+        OutputFormat::Taskwarrior => write_taskwarrior(out, &records, &config.country_code)?,
+        OutputFormat::ScriptFilter => write_script_filter(out, &records, config.today)?,
+        OutputFormat::Waybar => unreachable!("the waybar module is written by write_waybar"),
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Formats that hold several countries in one document. The others are written country after country,
+// which already works for line based formats and for documents keyed by the country.
+pub fn lists_countries_together(format: OutputFormat) -> bool {
+    matches!(format, OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Xml)
+}
+
+// The holidays of several countries in one document. JSON and YAML are a list of the envelopes of the countries,
+// or an object keyed by the country with --no-envelope; CSV and TSV get a country column.
+pub fn write_countries_holidays(
+    out: &mut dyn Write,
+    countries: &[(ListConfig, Vec<Holiday>)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let countries: Vec<(&ListConfig, Vec<HolidayRecord>)> =
+        countries.iter().map(|(config, holidays)| (config, holiday_records(holidays, config))).collect();
+    let Some((config, _)) = countries.first() else {
+        return Ok(());
+    };
+    match config.output {
+        OutputFormat::Json if config.envelope => {
+            let envelopes: Vec<_> =
+                countries.iter().map(|(config, records)| envelope(json_records(records, config), config)).collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&envelopes)?)?;
+        }
+        OutputFormat::Json => {
+            let document: BTreeMap<&str, JsonRecords> = countries
+                .iter()
+                .map(|(config, records)| (config.country_code.as_str(), json_records(records, config)))
+                .collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+        }
+        OutputFormat::Yaml if config.envelope => {
+            let envelopes: Vec<_> = countries.iter().map(|(config, records)| envelope(records, config)).collect();
+            write!(out, "{}", serde_yaml::to_string(&envelopes)?)?;
+        }
+        OutputFormat::Yaml => {
+            let document: BTreeMap<&str, &Vec<HolidayRecord>> =
+                countries.iter().map(|(config, records)| (config.country_code.as_str(), records)).collect();
+            write!(out, "{}", serde_yaml::to_string(&document)?)?;
+        }
+        OutputFormat::Csv => write_csv(out, &countries)?,
+        OutputFormat::Tsv => write_tsv(out, &countries)?,
+        OutputFormat::Xml => write_xml(out, &countries)?,
+        _ => unreachable!("the other formats are written country after country"),
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// The JSON object of a waybar custom module
+#[derive(Serialize)]
+struct WaybarModule {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
+
+// Shows today's holiday or the next one. The next year is only fetched once the current one is over,
+// so a status bar that polls every minute is answered from the cache
+pub fn write_waybar(out: &mut dyn Write, holidays: &[Holiday], today: NaiveDate, language: Language) -> Result<(), Box<dyn std::error::Error>> {
+    let next_holiday = holidays
+        .iter()
+        .map(|holiday| (holiday.date, holiday))
+        .filter(|(date, _)| *date >= today)
+        .min_by_key(|(date, _)| *date);
+
+    let module = match next_holiday {
+        Some((date, holiday)) => {
+            let name = holiday.display_name(language);
+            let days = (date - today).num_days();
+            WaybarModule {
+                text: if days == 0 { format!("{} today", name) } else { format!("{} in {}d", name, days) },
+                tooltip: format!("{} — {} ({})", holiday.date, name, holiday.types.join(", ")),
+                class: if days == 0 { "holiday-today" } else { "holiday-soon" },
+            }
+        }
+        // Waybar still needs a valid object when nothing is left
+        None => WaybarModule {
+            text: String::new(),
+            tooltip: "No upcoming holidays".to_string(),
+            class: "holiday-none",
+        },
+    };
+    writeln!(out, "{}", serde_json::to_string(&module)?)?;
+    Ok(())
+}
+
+// The holidays of the next days in every country, in date order with the country in front of the name
+pub fn write_worldwide(out: &mut dyn Write, holidays: &[WorldwideHoliday], format: CommandFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        CommandFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(holidays)?)?,
+        CommandFormat::Text => {
+            if holidays.is_empty() {
+                writeln!(out, "No holidays in the next days.")?;
+            }
+            for worldwide in holidays {
+                let holiday = &worldwide.holiday;
+                let weekday = weekday_name(holiday.date, false);
+                writeln!(out, "{} ({})  {}  {}", holiday.date, weekday, worldwide.country_code, holiday.display_name(Language::Both))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Prints a month grid like cal(1) with holidays in brackets, followed by a legend of the marked days
+pub fn write_calendar(
+    out: &mut dyn Write,
+    holidays: &[Holiday],
+    year: i32,
+    month: u32,
+    week_start: WeekStart,
+    language: Language,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let first_day = NaiveDate::from_ymd_opt(year, month, 1).ok_or("invalid calendar month")?;
+    let next_month_first_day = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or("invalid calendar month")?;
+    let days_in_month = (next_month_first_day - first_day).num_days() as u32;
+
+    let month_holidays: Vec<(NaiveDate, &Holiday)> = holidays
+        .iter()
+        .map(|holiday| (holiday.date, holiday))
+        .filter(|(date, _)| date.year() == year && date.month() == month)
+        .collect();
+
+    let (weekday_names, offset) = match week_start {
+        WeekStart::Monday => (["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"], first_day.weekday().num_days_from_monday()),
+        WeekStart::Sunday => (["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"], first_day.weekday().num_days_from_sunday()),
+    };
+
+    // Every day takes four columns so that bracketed days keep the grid aligned
+    writeln!(out, "{}", format!("{:^28}", first_day.format("%B %Y").to_string()).trim_end())?;
+    writeln!(out, "{}", weekday_names.map(|name| format!(" {} ", name)).concat().trim_end())?;
+    let mut line = "    ".repeat(offset as usize);
+    for day in 1..=days_in_month {
+        if month_holidays.iter().any(|(date, _)| date.day() == day) {
+            line.push_str(&format!("[{:>2}]", day));
+        } else {
+            line.push_str(&format!(" {:>2} ", day));
+        }
+        if (offset + day) % 7 == 0 {
+            writeln!(out, "{}", line.trim_end())?;
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    writeln!(out)?;
+    if month_holidays.is_empty() {
+        writeln!(out, "No holidays in {}", first_day.format("%B %Y"))?;
+    }
+    for (date, holiday) in &month_holidays {
+        writeln!(out, "{}  {}", date.format("%a %d %b"), holiday.display_name(language))?;
+    }
+    Ok(())
+}
+
+fn write_holiday(out: &mut dyn Write, record: &HolidayRecord, config: &ListConfig, style: &str, indent: &str) -> std::io::Result<()> {
+    let holiday = record.holiday;
+    let relative = format!(" ({})", relative_time(holiday.date, config.today));
+    let past = config.mark_past && holiday.date < config.today;
+    let observed = holiday.observed.as_ref().map(|observed| format!(" (observed {})", observed)).unwrap_or_default();
+    writeln!(
+        out,
+        "{}{}Date: {} ({}), Name: {}, Counties: {}, Types: {}{}{}{}{}{}",
+        indent,
+        style,
+        holiday.date,
+        weekday_cell(record),
+        record.display_name,
+        holiday.counties_text(),
+        paint_types(&holiday.types.join(", "), config.color),
+        observed,
+        relative,
+        if past { " (past)" } else { "" },
+        if record.ignored { " (ignored)" } else { "" },
+        if style.is_empty() { "" } else { RESET }
+    )
+}
+
+// The --fields variant of the text line, without the relative time and past marker
+fn write_holiday_fields(
+    out: &mut dyn Write,
+    record: &HolidayRecord,
+    fields: &[Field],
+    config: &ListConfig,
+    style: &str,
+    indent: &str,
+) -> std::io::Result<()> {
+    let line = fields
+        .iter()
+        .map(|field| {
+            let value = field_display(record, *field, config);
+            let value = if *field == Field::Types { paint_types(&value, config.color) } else { value };
+            format!("{}: {}", field.label(), value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    writeln!(out, "{}{}{}{}", indent, style, line, if style.is_empty() { "" } else { RESET })
+}
+
+// Terminals draw a flag two columns wide, followed by a space
+const FLAG_WIDTH: usize = 3;
+
+// DE becomes 🇩🇪. Codes that are not two letters have no flag and are printed as they are
+fn country_flag(country_code: &str) -> String {
+    if country_code.len() != 2 || !country_code.chars().all(|character| character.is_ascii_alphabetic()) {
+        return country_code.to_string();
+    }
+    country_code
+        .to_ascii_uppercase()
+        .chars()
+        .filter_map(|letter| char::from_u32(0x1F1E6 + (letter as u32 - 'A' as u32)))
+        .collect()
+}
+
+// Prefix of every text line and table row with --flags
+fn flag_prefix(config: &ListConfig) -> String {
+    if config.flags {
+        format!("{} ", country_flag(&config.country_code))
+    } else {
+        String::new()
+    }
+}
+
+// Describes the distance to a date like "tomorrow", "in 5 days" or "3 weeks ago"
+fn relative_time(date: NaiveDate, today: NaiveDate) -> String {
+    let days = (date - today).num_days();
+    let distance = match days.abs() {
+        0 => return "today".to_string(),
+        1 => return if days > 0 { "tomorrow" } else { "yesterday" }.to_string(),
+        count @ 2..=13 => format!("{} days", count),
+        count @ 14..=59 => format!("{} weeks", count / 7),
+        count => format!("{} months", count / 30),
+    };
+    if days > 0 {
+        format!("in {}", distance)
+    } else {
+        format!("{} ago", distance)
+    }
+}
+
+// Hints keyed by holiday date for --bridge-days. Holidays that share their free days with an
+// earlier listed holiday (e.g. Dec 25 and 26) get no hint of their own.
+fn bridge_hints(records: &[HolidayRecord], today: NaiveDate) -> HashMap<NaiveDate, String> {
+    // Only national holidays are free for everyone
+    let national_days: HashSet<NaiveDate> = records
+        .iter()
+        .filter(|record| record.holiday.is_national())
+        .map(|record| record.holiday.date)
+        .collect();
+
+    let mut hints = HashMap::new();
+    let mut seen_blocks = HashSet::new();
+    for record in records {
+        let date = record.holiday.date;
+        if date <= today {
+            continue;
+        }
+        let mut off_days = national_days.clone();
+        off_days.insert(date);
+
+        let free_days = bridge::free_days_around(date, &off_days);
+        if !seen_blocks.insert(free_days.start) {
+            continue;
+        }
+        let hint = match bridge::bridge_day(free_days, &off_days) {
+            Some(bridge) => format!(
+                "Bridge day: take {} {} off → {}-day weekend",
+                bridge.bridge_day.format("%a"),
+                bridge.bridge_day,
+                bridge.free_days.length()
+            ),
+            None if free_days.length() >= 3 => format!("Long weekend: {} days off", free_days.length()),
+            None => continue,
+        };
+        hints.insert(date, hint);
+    }
+    hints
+}
+
+// Weekend days are marked with an asterisk that is explained in a footnote
+fn weekday_cell(record: &HolidayRecord) -> String {
+    if record.weekend {
+        format!("{}*", record.weekday)
+    } else {
+        record.weekday.clone()
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const DEFAULT_FOREGROUND: &str = "\x1b[39m";
+
+// The soonest upcoming holiday is highlighted and county restricted holidays are dimmed
+fn row_style(holiday: &Holiday, soonest_date: Option<NaiveDate>, color: bool) -> &'static str {
+    if !color {
+        ""
+    } else if soonest_date == Some(holiday.date) {
+        BOLD
+    } else if !holiday.is_national() {
+        DIM
+    } else {
+        ""
+    }
+}
+
+fn soonest_upcoming_date(records: &[HolidayRecord], today: NaiveDate) -> Option<NaiveDate> {
+    records
+        .iter()
+        .map(|record| record.holiday.date)
+        .filter(|date| *date >= today)
+        .min()
+}
+
+// Colors each type in a ", " separated list, only the foreground is reset so the row style is kept
+fn paint_types(types: &str, color: bool) -> String {
+    if !color {
+        return types.to_string();
+    }
+    types
+        .split(", ")
+        .map(|holiday_type| {
+            let foreground = match holiday_type {
+                "Public" => "\x1b[32m",
+                "Bank" => "\x1b[36m",
+                "School" => "\x1b[33m",
+                _ => "\x1b[35m",
+            };
+            format!("{}{}{}", foreground, holiday_type, DEFAULT_FOREGROUND)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn write_table(out: &mut dyn Write, records: &[HolidayRecord], config: &ListConfig) -> std::io::Result<()> {
+    let fields = config.fields.as_deref().unwrap_or(&TABLE_FIELDS);
+    let header: Vec<String> = fields.iter().map(|field| field.label().to_string()).collect();
+    let rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|record| {
+            fields
+                .iter()
+                .map(|field| match field {
+                    Field::Counties => truncate_to_width(&field_display(record, *field, config), config.counties_width),
+                    _ => field_display(record, *field, config),
+                })
+                .collect()
+        })
+        .collect();
+    let types_column = if config.color { fields.iter().position(|field| *field == Field::Types) } else { None };
+
+    // Widths are measured in terminal columns, not bytes, so non-ASCII names stay aligned
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.width()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let grouped = config.group_by == Some(GroupBy::Month);
+    let indent = if grouped { "  " } else { "" };
+    let flag = flag_prefix(config);
+    let row_indent = format!("{}{}", indent, flag);
+    // The header rows are shifted by the width of the flag so the columns stay aligned
+    let header_indent = format!("{}{}", indent, if config.flags { " ".repeat(FLAG_WIDTH) } else { String::new() });
+    write_table_row(out, &header_indent, &header, &widths, "", "", None)?;
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    write_table_row(out, &header_indent, &separator, &widths, "", "", None)?;
+    let soonest_date = soonest_upcoming_date(records, config.today);
+    let write_record_row = |out: &mut dyn Write, index: usize| {
+        let record = &records[index];
+        let past = config.mark_past && is_past(record.holiday, config.today);
+        let marker = match (past, record.ignored) {
+            (true, true) => "(past, ignored)",
+            (true, false) => "(past)",
+            (false, true) => "(ignored)",
+            (false, false) => "",
+        };
+        let style = row_style(record.holiday, soonest_date, config.color);
+        write_table_row(out, &row_indent, &rows[index], &widths, marker, style, types_column)
+    };
+    if grouped {
+        for group in month_groups(records, config.show_empty_months) {
+            writeln!(out, "{}", group.month.format("%B %Y"))?;
+            if group.records.is_empty() {
+                writeln!(out, "{}{}", indent, NO_HOLIDAYS_IN_MONTH)?;
+            }
+            for record in group.records {
+                let index = records.iter().position(|other| std::ptr::eq(other, record)).expect("record is listed");
+                write_record_row(out, index)?;
+            }
+        }
+    } else {
+        for index in 0..records.len() {
+            write_record_row(out, index)?;
+        }
+    }
+    if fields.contains(&Field::Weekday) && records.iter().any(|record| record.weekend) {
+        writeln!(out, "{}", WEEKEND_FOOTNOTE)?;
+    }
+    Ok(())
+}
+
+// The cell at `types_column` is colored when given
+fn write_table_row(
+    out: &mut dyn Write,
+    indent: &str,
+    cells: &[String],
+    widths: &[usize],
+    marker: &str,
+    style: &str,
+    types_column: Option<usize>,
+) -> std::io::Result<()> {
+    let mut line = String::new();
+    for (index, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        // Padding is computed from the plain cell so escape codes don't break the alignment
+        if Some(index) == types_column {
+            line.push_str(&paint_types(cell, true));
+        } else {
+            line.push_str(cell);
+        }
+        line.push_str(&" ".repeat(width - cell.width() + 2));
+    }
+    let line = format!("{}{}", line, marker).trim_end().to_string();
+    if style.is_empty() {
+        writeln!(out, "{}{}", indent, line)
+    } else {
+        writeln!(out, "{}{}{}{}", indent, style, line, RESET)
+    }
+}
+
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    // Leave one column for the ellipsis
+    let mut truncated = String::new();
+    let mut width = 0;
+    for character in text.chars() {
+        let character_width = character.width().unwrap_or(0);
+        if width + character_width + 1 > max_width {
+            break;
+        }
+        truncated.push(character);
+        width += character_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+fn write_csv(out: &mut dyn Write, countries: &[(&ListConfig, Vec<HolidayRecord>)]) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = delimited_fields(countries);
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(fields.iter().map(|field| field.name()))?;
+    for (config, records) in countries {
+        for record in records {
+            // Multiple counties and types are kept in a single cell
+            writer.write_record(fields.iter().map(|field| field_text(record, *field, config, ";")))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// A flat object per line for line based consumers
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    country: &'a str,
+    date: NaiveDate,
+    name: &'a str,
+    counties: Option<&'a [String]>,
+    types: &'a [String],
+    days_until: i64,
+}
+
+fn write_ndjson(out: &mut dyn Write, records: &[HolidayRecord], config: &ListConfig) -> Result<(), Box<dyn std::error::Error>> {
+    for record in records {
+        let holiday = record.holiday;
+        let line = NdjsonLine {
+            country: &config.country_code,
+            date: holiday.date,
+            name: &record.display_name,
+            counties: holiday.counties.as_deref(),
+            types: &holiday.types,
+            days_until: (holiday.date - config.today).num_days(),
+        };
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+        // Flushed per line so a reader that stops early still gets complete lines
+        out.flush()?;
+    }
+    Ok(())
+}
+
+// TOML has no null, so national holidays simply have no counties key
+#[derive(Serialize)]
+struct TomlHoliday<'a> {
+    date: NaiveDate,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counties: Option<&'a [String]>,
+    types: &'a [String],
+}
+
+#[derive(Serialize)]
+struct TomlCountry<'a> {
+    holiday: Vec<TomlHoliday<'a>>,
+}
+
+fn write_toml(out: &mut dyn Write, records: &[HolidayRecord], country_code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let holidays = records
+        .iter()
+        .map(|record| TomlHoliday {
+            date: record.holiday.date,
+            name: &record.display_name,
+            counties: record.holiday.counties.as_deref(),
+            types: &record.holiday.types,
+        })
+        .collect();
+    // Written as [[country.DE.holiday]] so documents of several countries can be concatenated
+    let document = BTreeMap::from([("country", BTreeMap::from([(country_code, TomlCountry { holiday: holidays })]))]);
+    write!(out, "{}", toml::to_string(&document)?)?;
+    Ok(())
+}
+
+fn write_org(out: &mut dyn Write, records: &[HolidayRecord], config: &ListConfig) -> Result<(), Box<dyn std::error::Error>> {
+    for record in records {
+        let holiday = record.holiday;
+        let date = holiday.date;
+        writeln!(out, "* {}", record.display_name.replace('\n', " "))?;
+        // org-agenda expects the English weekday abbreviation that matches the date
+        writeln!(out, "  SCHEDULED: <{}>", date.format("%Y-%m-%d %a"))?;
+        writeln!(out, "  :PROPERTIES:")?;
+        writeln!(out, "  :COUNTRY:  {}", config.country_code)?;
+        if let Some(category) = &config.org_category {
+            writeln!(out, "  :CATEGORY: {}", category)?;
+        }
+        writeln!(out, "  :TYPES:    {}", holiday.types.join(", "))?;
+        writeln!(out, "  :COUNTIES: {}", holiday.counties_text())?;
+        writeln!(out, "  :END:")?;
+    }
+    Ok(())
+}
+
+fn write_remind(out: &mut dyn Write, records: &[HolidayRecord], country_code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for record in records {
+        let holiday = record.holiday;
+        let date = holiday.date;
+        // chrono always formats %b in English, which is what remind expects
+        writeln!(
+            out,
+            "REM {} MSG {}",
+            date.format("%-d %b %Y"),
+            escape_remind(&format!("{} ({}, {})", record.display_name, country_code, holiday.types.join(", ")))
+        )?;
+    }
+    Ok(())
+}
+
+// '%' starts a substitution and '[' an expression in a remind body, line breaks would end the reminder
+fn escape_remind(text: &str) -> String {
+    text.replace('%', "%%").replace('[', "[\"[\"]").replace(['\n', '\r'], " ")
+}
+
+#[derive(Serialize)]
+struct TaskwarriorTask {
+    uuid: String,
+    description: String,
+    due: String,
+    project: String,
+    tags: Vec<String>,
+}
+
+fn write_taskwarrior(out: &mut dyn Write, records: &[HolidayRecord], country_code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let tasks: Vec<TaskwarriorTask> = records
+        .iter()
+        .map(|record| {
+            let holiday = record.holiday;
+            TaskwarriorTask {
+                // Stable across runs so `task import` updates the task instead of adding a duplicate
+                uuid: stable_uuid(&format!("{}|{}|{}", country_code, holiday.date, holiday.name)),
+                description: record.display_name.clone(),
+                due: format!("{}T00:00:00Z", holiday.date),
+                project: format!("holidays.{}", country_code),
+                tags: holiday.types.iter().map(|holiday_type| holiday_type.to_lowercase()).collect(),
+            }
+        })
+        .collect();
+    writeln!(out, "{}", serde_json::to_string_pretty(&tasks)?)?;
+    Ok(())
+}
+
+// A UUID built from two stable hashes, with the version 8 (custom) and RFC variant bits set
+fn stable_uuid(text: &str) -> String {
+    let high = (stable_hash(text) & !0xf000) | 0x8000;
+    let low = (stable_hash(&format!("{}|uuid", text)) & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+// Holidays at most this many days away get SOON_ICON in the script filter output
+const SOON_DAYS: i64 = 7;
+const SOON_ICON: &str = "soon.png";
+
+#[derive(Serialize)]
+struct ScriptFilter<'a> {
+    items: Vec<ScriptFilterItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct ScriptFilterItem<'a> {
+    title: &'a str,
+    subtitle: String,
+    arg: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<ScriptFilterIcon>,
+}
+
+#[derive(Serialize)]
+struct ScriptFilterIcon {
+    path: &'static str,
+}
+
+fn write_script_filter(out: &mut dyn Write, records: &[HolidayRecord], today: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    let items = records
+        .iter()
+        .map(|record| {
+            let holiday = record.holiday;
+            let days = (holiday.date - today).num_days();
+            let distance = match days {
+                0 => " · today".to_string(),
+                1 => " · tomorrow".to_string(),
+                days if days > 0 => format!(" · in {} days", days),
+                days => format!(" · {} days ago", -days),
+            };
+            ScriptFilterItem {
+                title: &record.display_name,
+                subtitle: format!("{}{} · {}", holiday.date, distance, holiday.types.join(", ")),
+                arg: holiday.date,
+                icon: (0..=SOON_DAYS)
+                    .contains(&days)
+                    .then_some(ScriptFilterIcon { path: SOON_ICON }),
+            }
+        })
+        .collect();
+    writeln!(out, "{}", serde_json::to_string(&ScriptFilter { items })?)?;
+    Ok(())
+}
+
+fn write_tsv(out: &mut dyn Write, countries: &[(&ListConfig, Vec<HolidayRecord>)]) -> std::io::Result<()> {
+    let fields = delimited_fields(countries);
+    if countries[0].0.header {
+        writeln!(out, "{}", fields.iter().map(|field| field.name()).collect::<Vec<_>>().join("\t"))?;
+    }
+    for (config, records) in countries {
+        for record in records {
+            let cells: Vec<String> = fields.iter().map(|field| tsv_field(&field_text(record, *field, config, ","))).collect();
+            writeln!(out, "{}", cells.join("\t"))?;
+        }
+    }
+    Ok(())
+}
+
+// Tabs and line breaks would split a record, so they are replaced with spaces
+fn tsv_field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+fn write_html(out: &mut dyn Write, records: &[HolidayRecord], config: &ListConfig) -> std::io::Result<()> {
+    if config.full_page {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Holidays for {}</title>", escape_markup(&config.country_code))?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+    }
+    writeln!(out, "<table class=\"holidays\">")?;
+    writeln!(out, "  <thead>")?;
+    writeln!(out, "    <tr><th>Date</th><th>Weekday</th><th>Name</th><th>Counties</th><th>Types</th></tr>")?;
+    writeln!(out, "  </thead>")?;
+    writeln!(out, "  <tbody>")?;
+    for record in records {
+        let holiday = record.holiday;
+        // data-date lets scripts on the page find today's row
+        writeln!(
+            out,
+            "    <tr data-date=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            holiday.date,
+            holiday.date,
+            escape_markup(&record.weekday),
+            escape_markup(&record.display_name),
+            escape_markup(&holiday.counties_text()),
+            escape_markup(&holiday.types.join(", "))
+        )?;
+    }
+    writeln!(out, "  </tbody>")?;
+    writeln!(out, "</table>")?;
+    if config.full_page {
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")?;
+    }
+    Ok(())
+}
+
+// Several countries are wrapped in a <countries> element, so the document keeps a single root
+fn write_xml(out: &mut dyn Write, countries: &[(&ListConfig, Vec<HolidayRecord>)]) -> std::io::Result<()> {
+    let (pretty, today) = (countries[0].0.pretty, countries[0].0.today);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let mut element = |depth: usize, text: &str| {
+        if pretty {
+            xml.push_str(&"  ".repeat(depth));
+        }
+        xml.push_str(text);
+        if pretty {
+            xml.push('\n');
+        }
+    };
+
+    let several_countries = countries.len() > 1;
+    let base = usize::from(several_countries);
+    if several_countries {
+        element(0, &format!("<countries generated=\"{}\">", today));
+    }
+    for (config, records) in countries {
+        element(
+            base,
+            &format!(
+                "<holidays country=\"{}\" generated=\"{}\">",
+                escape_markup(&config.country_code),
+                config.today
+            ),
+        );
+        for record in records {
+            let holiday = record.holiday;
+            element(
+                base + 1,
+                &format!(
+                    "<holiday date=\"{}\" weekday=\"{}\">",
+                    holiday.date,
+                    escape_markup(&record.weekday)
+                ),
+            );
+            element(base + 2, &format!("<name>{}</name>", escape_markup(&holiday.name)));
+            if let Some(local_name) = &holiday.local_name {
+                element(base + 2, &format!("<localName>{}</localName>", escape_markup(local_name)));
+            }
+            // National holidays have no counties element
+            if let Some(counties) = &holiday.counties {
+                element(base + 2, "<counties>");
+                for county in counties {
+                    element(base + 3, &format!("<county>{}</county>", escape_markup(county)));
+                }
+                element(base + 2, "</counties>");
+            }
+            element(base + 2, "<types>");
+            for holiday_type in &holiday.types {
+                element(base + 3, &format!("<type>{}</type>", escape_markup(holiday_type)));
+            }
+            element(base + 2, "</types>");
+            element(base + 1, "</holiday>");
+        }
+        element(base, "</holidays>");
+    }
+    if several_countries {
+        element(0, "</countries>");
+    }
+
+    if !pretty {
+        xml.push('\n');
+    }
+    out.write_all(xml.as_bytes())
+}
+
+// Escapes text for HTML and XML output
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn write_ics(out: &mut dyn Write, records: &[HolidayRecord], country_code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    write_ics_line(out, "BEGIN:VCALENDAR")?;
+    write_ics_line(out, "VERSION:2.0")?;
+    write_ics_line(out, "PRODID:-//get_holiday//Public Holidays//EN")?;
+    write_ics_line(out, "CALSCALE:GREGORIAN")?;
+    for record in records {
+        let holiday = record.holiday;
+        let date = holiday.date;
+        let counties = holiday.counties_text();
+        // The UID only depends on the holiday itself so re-importing the file updates existing events
+        let uid = stable_hash(&format!("{}|{}|{}", country_code, holiday.date, holiday.name));
+
+        write_ics_line(out, "BEGIN:VEVENT")?;
+        write_ics_line(out, &format!("UID:{:016x}@get_holiday", uid))?;
+        write_ics_line(out, &format!("DTSTAMP:{}", timestamp))?;
+        write_ics_line(out, &format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")))?;
+        write_ics_line(out, &format!("DTEND;VALUE=DATE:{}", (date + Duration::days(1)).format("%Y%m%d")))?;
+        write_ics_line(out, &format!("SUMMARY:{}", escape_ics_text(&record.display_name)))?;
+        write_ics_line(
+            out,
+            &format!(
+                "DESCRIPTION:{}",
+                escape_ics_text(&format!("Types: {}\nCounties: {}", holiday.types.join(", "), counties))
+            ),
+        )?;
+        write_ics_line(out, "TRANSP:TRANSPARENT")?;
+        write_ics_line(out, "END:VEVENT")?;
+    }
+    write_ics_line(out, "END:VCALENDAR")?;
+    Ok(())
+}
+
+// Content lines are folded at 75 octets and end with CRLF (RFC 5545, section 3.1)
+fn write_ics_line(out: &mut dyn Write, line: &str) -> std::io::Result<()> {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut line_length = 0;
+    for character in line.chars() {
+        if line_length + character.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(character);
+        line_length += character.len_utf8();
+    }
+    folded.push_str("\r\n");
+    out.write_all(folded.as_bytes())
+}
+
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// FNV-1a, used instead of the std hasher because its output must not change between builds
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
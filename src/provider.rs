@@ -0,0 +1,439 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::Holiday;
+
+// The APIs the holidays can be requested from
+#[derive(clap::ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Nager,        // https://date.nager.at, the default
+    Openholidays, // https://openholidaysapi.org, with school holidays per subdivision
+    Calendarific, // https://calendarific.com, needs an API key and has many observances
+    Holidayapi,   // https://holidayapi.com, needs an API key, free keys only get last year
+}
+
+impl Provider {
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Nager => "nager",
+            Provider::Openholidays => "openholidays",
+            Provider::Calendarific => "calendarific",
+            Provider::Holidayapi => "holidayapi",
+        }
+    }
+
+    pub fn default_url(self) -> &'static str {
+        match self {
+            Provider::Nager => crate::DEFAULT_API_URL,
+            Provider::Openholidays => "https://openholidaysapi.org",
+            Provider::Calendarific => "https://calendarific.com",
+            Provider::Holidayapi => "https://holidayapi.com",
+        }
+    }
+
+    // The environment variable and config file key of the API key, for the providers that need one
+    pub fn api_key_names(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Provider::Nager | Provider::Openholidays => None,
+            Provider::Calendarific => Some(("CALENDARIFIC_API_KEY", "calendarific_api_key")),
+            Provider::Holidayapi => Some(("HOLIDAYAPI_API_KEY", "holidayapi_api_key")),
+        }
+    }
+
+    pub fn has_school_holidays(self) -> bool {
+        self == Provider::Openholidays
+    }
+
+    pub fn has_subdivisions(self) -> bool {
+        matches!(self, Provider::Openholidays | Provider::Calendarific | Provider::Holidayapi)
+    }
+}
+
+// Which holidays are requested, only OpenHolidays has school holidays
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HolidayKind {
+    Public,
+    School,
+}
+
+impl HolidayKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HolidayKind::Public => "public",
+            HolidayKind::School => "school",
+        }
+    }
+}
+
+// The provider together with the options that change what it returns
+#[derive(Debug, Clone)]
+pub struct ProviderOptions {
+    pub provider: Provider,
+    pub kind: HolidayKind,
+    pub subdivision: Option<String>, // Upper case, e.g. "DE-BY"
+    pub base_url: String,
+    pub api_key: Option<String>, // For the providers with Provider::api_key_names
+}
+
+impl ProviderOptions {
+    // Cache entries are namespaced with this, e.g. "openholidays/school/DE-BY", so providers don't mix
+    pub fn cache_key(&self) -> String {
+        let mut key = match self.provider {
+            Provider::Nager | Provider::Calendarific | Provider::Holidayapi => self.provider.name().to_string(),
+            Provider::Openholidays => format!("{}/{}", self.provider.name(), self.kind.name()),
+        };
+        if let Some(subdivision) = &self.subdivision {
+            key.push('/');
+            key.push_str(subdivision);
+        }
+        key
+    }
+
+    pub fn holidays_url(&self, country_code: &str, year: i32) -> String {
+        match self.provider {
+            Provider::Nager => format!("{}/api/v3/publicholidays/{}/{}", self.base_url, year, country_code),
+            Provider::Openholidays => {
+                let endpoint = match self.kind {
+                    HolidayKind::Public => "PublicHolidays",
+                    HolidayKind::School => "SchoolHolidays",
+                };
+                let mut url = format!(
+                    "{}/{}?countryIsoCode={}&validFrom={}-01-01&validTo={}-12-31",
+                    self.base_url, endpoint, country_code, year, year
+                );
+                if let Some(subdivision) = &self.subdivision {
+                    url.push_str("&subdivisionCode=");
+                    url.push_str(subdivision);
+                }
+                url
+            }
+            Provider::Calendarific => {
+                let mut url = format!(
+                    "{}/api/v2/holidays?api_key={}&country={}&year={}",
+                    self.base_url,
+                    self.api_key.as_deref().unwrap_or_default(),
+                    country_code,
+                    year
+                );
+                if let Some(subdivision) = &self.subdivision {
+                    url.push_str("&location=");
+                    url.push_str(&subdivision.to_lowercase());
+                }
+                url
+            }
+            // A subdivision is given instead of the country
+            Provider::Holidayapi => format!(
+                "{}/v1/holidays?key={}&country={}&year={}",
+                self.base_url,
+                self.api_key.as_deref().unwrap_or_default(),
+                self.subdivision.as_deref().unwrap_or(country_code),
+                year
+            ),
+        }
+    }
+}
+
+// Entries written before there were several providers came from Nager
+pub fn default_cache_key() -> String {
+    Provider::Nager.name().to_string()
+}
+
+// A holiday or school holiday period as sent by OpenHolidays
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenHoliday {
+    #[serde(deserialize_with = "crate::deserialize_date")]
+    start_date: NaiveDate,
+    #[serde(deserialize_with = "crate::deserialize_date")]
+    end_date: NaiveDate,
+    #[serde(rename = "type")]
+    holiday_type: String,
+    name: Vec<LocalizedText>,
+    #[serde(default)]
+    nationwide: bool,
+    #[serde(default)]
+    subdivisions: Vec<Subdivision>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LocalizedText {
+    language: String,
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Subdivision {
+    code: String,
+}
+
+// A period such as the summer holidays becomes one holiday per day, so every date based option keeps
+// working. Days outside `year` are left out, they belong to the periods of the neighbouring year.
+pub fn expand_open_holidays(open_holidays: Vec<OpenHoliday>, year: i32) -> Vec<Holiday> {
+    let mut holidays = Vec::new();
+    for open_holiday in open_holidays {
+        let english_name = open_holiday.name.iter().find(|text| text.language == "EN").or(open_holiday.name.first());
+        let local_name = open_holiday.name.iter().find(|text| text.language != "EN").or(english_name);
+        let Some(english_name) = english_name else {
+            continue;
+        };
+        let counties: Vec<String> = open_holiday.subdivisions.iter().map(|subdivision| subdivision.code.clone()).collect();
+
+        let mut date = open_holiday.start_date;
+        while date <= open_holiday.end_date {
+            if date.year() == year {
+                holidays.push(Holiday {
+                    date,
+                    local_name: local_name.map(|text| text.text.clone()),
+                    name: english_name.text.clone(),
+                    counties: (!counties.is_empty()).then(|| counties.clone()),
+                    global: Some(open_holiday.nationwide),
+                    fixed: None,
+                    types: vec![open_holiday.holiday_type.clone()],
+                    observed: None,
+                });
+            }
+            date += Duration::days(1);
+        }
+    }
+    holidays.sort_by_key(|holiday| holiday.date);
+    holidays
+}
+
+// Calendarific answers every request with a meta block, errors often come with status 200
+#[derive(Deserialize, Debug)]
+pub struct CalendarificResponse {
+    meta: CalendarificMeta,
+    #[serde(default)]
+    response: serde_json::Value, // An empty list instead of an object on errors
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarificMeta {
+    code: u16,
+    error_type: Option<String>,
+    error_detail: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarificHolidays {
+    #[serde(default)]
+    holidays: Vec<CalendarificHoliday>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarificHoliday {
+    name: String,
+    date: CalendarificDate,
+    #[serde(rename = "type", default)]
+    types: Vec<String>,
+    #[serde(default)]
+    states: serde_json::Value, // "All" for the whole country, otherwise a list of states
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarificDate {
+    iso: String, // A date, or a date and time for holidays that start at a certain hour
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarificState {
+    iso: String, // e.g. "de-by"
+}
+
+// The error of a Calendarific answer that has no holidays
+#[derive(Debug)]
+pub struct ProviderError {
+    pub code: u16,
+    pub message: String,
+}
+
+// Calendarific lists a holiday once for every group of states, these entries are merged into one.
+// Its types are mapped to the names the other providers use where there is a counterpart.
+pub fn calendarific_holidays(response: CalendarificResponse) -> Result<Vec<Holiday>, ProviderError> {
+    if response.meta.code != 200 {
+        let message = [response.meta.error_type, response.meta.error_detail].into_iter().flatten().collect::<Vec<_>>();
+        return Err(ProviderError { code: response.meta.code, message: message.join(": ").trim_end_matches('.').to_string() });
+    }
+    let holidays: CalendarificHolidays = serde_json::from_value(response.response).map_err(|err| ProviderError {
+        code: response.meta.code,
+        message: format!("unexpected response: {}", err),
+    })?;
+
+    let mut merged: BTreeMap<(NaiveDate, String), Holiday> = BTreeMap::new();
+    for holiday in holidays.holidays {
+        let iso: String = holiday.date.iso.chars().take(10).collect();
+        let date = NaiveDate::parse_from_str(&iso, "%Y-%m-%d").map_err(|_| ProviderError {
+            code: response.meta.code,
+            message: format!("unexpected response: invalid date '{}', expected YYYY-MM-DD", holiday.date.iso),
+        })?;
+        let counties: Option<Vec<String>> = serde_json::from_value::<Vec<CalendarificState>>(holiday.states)
+            .ok()
+            .map(|states| states.into_iter().map(|state| state.iso.to_uppercase()).collect());
+        let types: Vec<String> = holiday.types.iter().map(|holiday_type| calendarific_type(holiday_type)).collect();
+
+        let entry = merged.entry((date, holiday.name.clone())).or_insert_with(|| Holiday {
+            date,
+            local_name: None,
+            name: holiday.name,
+            counties: Some(Vec::new()),
+            global: Some(false),
+            fixed: None,
+            types: Vec::new(),
+            observed: None,
+        });
+        match counties {
+            Some(counties) if entry.global == Some(false) => entry.counties.get_or_insert_with(Vec::new).extend(counties),
+            Some(_) => {}
+            None => {
+                entry.global = Some(true);
+                entry.counties = None;
+            }
+        }
+        for holiday_type in types {
+            if !entry.types.contains(&holiday_type) {
+                entry.types.push(holiday_type);
+            }
+        }
+    }
+    Ok(merged.into_values().collect())
+}
+
+fn calendarific_type(holiday_type: &str) -> String {
+    match holiday_type {
+        "National holiday" | "Local holiday" | "Common local holiday" => "Public",
+        "Bank holiday" => "Bank",
+        "Optional holiday" => "Optional",
+        "Observance" | "Local observance" | "Christian" | "Season" | "Clock change/Daylight Saving Time" => "Observance",
+        other => other,
+    }
+    .to_string()
+}
+
+// holidayapi.com sends its errors as {"status": 402, "error": "..."} with the same HTTP status
+#[derive(Deserialize, Debug)]
+struct HolidayapiResponse {
+    error: Option<String>,
+    #[serde(default)]
+    holidays: Vec<HolidayapiHoliday>,
+}
+
+#[derive(Deserialize, Debug)]
+struct HolidayapiHoliday {
+    name: String,
+    #[serde(deserialize_with = "crate::deserialize_date")]
+    date: NaiveDate,
+    #[serde(deserialize_with = "crate::deserialize_date")]
+    observed: NaiveDate,
+    public: bool,
+    #[serde(default)]
+    subdivisions: Vec<String>, // Only for paid accounts
+}
+
+// The day off is `observed`, which differs from `date` when a holiday is moved, it is kept next to the date.
+// Free accounts only get the holidays of last year, that error is explained instead of passed on.
+pub fn holidayapi_holidays(status: u16, body: &str, year: i32, current_year: i32) -> Result<Vec<Holiday>, ProviderError> {
+    let response: HolidayapiResponse = serde_json::from_str(body)
+        .map_err(|err| ProviderError { code: status, message: format!("unexpected response: {}", err) })?;
+    if let Some(error) = response.error {
+        let message = if error.to_lowercase().contains("last year") {
+            format!(
+                "free accounts only get the holidays of last year, use --year {} or a paid API key ({})",
+                current_year - 1,
+                error.trim_end_matches('.')
+            )
+        } else {
+            error.trim_end_matches('.').to_string()
+        };
+        return Err(ProviderError { code: status, message });
+    }
+    if !(200..300).contains(&status) {
+        return Err(ProviderError { code: status, message: format!("no holidays for {}", year) });
+    }
+
+    Ok(response
+        .holidays
+        .into_iter()
+        .map(|holiday| Holiday {
+            counties: (!holiday.subdivisions.is_empty()).then(|| holiday.subdivisions.clone()),
+            global: Some(holiday.subdivisions.is_empty()),
+            observed: (holiday.observed != holiday.date).then_some(holiday.observed),
+            date: holiday.date,
+            local_name: None,
+            name: holiday.name,
+            fixed: None,
+            types: vec![if holiday.public { "Public" } else { "Observance" }.to_string()],
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn holidayapi_observed_dates_are_kept() {
+        let holidays = holidayapi_holidays(200, include_str!("../tests/fixtures/holidayapi_holidays.json"), 2026, 2026).unwrap();
+        assert_eq!(holidays.len(), 3);
+        assert_eq!(holidays[0].observed, None);
+        let independence_day = &holidays[2];
+        assert_eq!(independence_day.date, date(2026, 7, 4));
+        assert_eq!(independence_day.observed, Some(date(2026, 7, 3)));
+        assert_eq!(independence_day.types, ["Public"]);
+        let patriots_day = &holidays[1];
+        assert_eq!(patriots_day.counties.as_deref(), Some(["US-MA".to_string(), "US-ME".to_string()].as_slice()));
+        assert_eq!(patriots_day.global, Some(false));
+        assert_eq!(patriots_day.types, ["Observance"]);
+    }
+
+    #[test]
+    fn holidayapi_free_accounts_are_told_to_use_last_year() {
+        let err = holidayapi_holidays(402, include_str!("../tests/fixtures/holidayapi_last_year.json"), 2026, 2026).unwrap_err();
+        assert_eq!(err.code, 402);
+        assert!(err.message.starts_with("free accounts only get the holidays of last year, use --year 2025"), "{}", err.message);
+        assert!(err.message.ends_with("(Free accounts are limited to last year's historical data only. Upgrade to premium for access to current and upcoming holidays)"), "{}", err.message);
+    }
+
+    #[test]
+    fn holidayapi_status_without_an_error_is_an_error() {
+        let err = holidayapi_holidays(404, include_str!("../tests/fixtures/holidayapi_no_error.json"), 2026, 2026).unwrap_err();
+        assert_eq!(err.code, 404);
+        assert_eq!(err.message, "no holidays for 2026");
+        let err = holidayapi_holidays(502, "<html>Bad Gateway</html>", 2026, 2026).unwrap_err();
+        assert_eq!(err.code, 502);
+        assert!(err.message.starts_with("unexpected response: "), "{}", err.message);
+    }
+
+    #[test]
+    fn calendarific_entries_of_several_states_are_merged() {
+        let response: CalendarificResponse = serde_json::from_str(include_str!("../tests/fixtures/calendarific_holidays.json")).unwrap();
+        let holidays = calendarific_holidays(response).unwrap();
+        let names: Vec<&str> = holidays.iter().map(|holiday| holiday.name.as_str()).collect();
+        assert_eq!(names, ["Epiphany", "Daylight Saving Time starts", "Christmas Day"]);
+        let epiphany = &holidays[0];
+        assert_eq!(epiphany.counties.as_deref(), Some(["DE-BW".to_string(), "DE-BY".to_string(), "DE-ST".to_string()].as_slice()));
+        assert_eq!(epiphany.global, Some(false));
+        assert_eq!(epiphany.types, ["Public"]);
+        // A date with a time is the day it starts
+        assert_eq!(holidays[1].date, date(2026, 3, 29));
+        let christmas = &holidays[2];
+        assert_eq!((christmas.counties.as_ref(), christmas.global), (None, Some(true)));
+        assert_eq!(christmas.types, ["Public", "Observance"]);
+    }
+
+    #[test]
+    fn calendarific_errors_keep_their_code_and_detail() {
+        let response: CalendarificResponse = serde_json::from_str(include_str!("../tests/fixtures/calendarific_error.json")).unwrap();
+        let err = calendarific_holidays(response).unwrap_err();
+        assert_eq!(err.code, 401);
+        assert_eq!(
+            err.message,
+            "auth failed: Missing or invalid api credentials. See https://calendarific.com/api-documentation for details"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::fs;
+
+use crate::output::weekday_name;
+use crate::{is_past, Holiday, ListConfig};
+
+// Variables available in a --template file, printed by --template-help
+const TEMPLATE_VARIABLES: [(&str, &str); 13] = [
+    ("country", "Country code, e.g. \"DE\""),
+    ("generated", "Date the report was generated, e.g. \"2026-10-14\""),
+    ("holidays", "List of the selected holidays with the fields below"),
+    ("holiday.date", "Date as YYYY-MM-DD"),
+    ("holiday.name", "English name"),
+    ("holiday.local_name", "Name in the country's language, may be none"),
+    ("holiday.display_name", "Name in the language chosen with --lang"),
+    ("holiday.counties", "List of county codes, none for national holidays"),
+    ("holiday.types", "List of holiday types, e.g. [\"Public\"]"),
+    ("holiday.weekday", "Weekday name, full with --long-weekdays"),
+    ("holiday.days_until", "Days from today, negative for past holidays"),
+    ("holiday.is_weekend", "True when the holiday falls on a Saturday or Sunday"),
+    ("holiday.is_past", "True when the holiday is before today"),
+];
+
+pub fn template_help() -> String {
+    let width = TEMPLATE_VARIABLES.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut help = String::from("Variables available in --template files (minijinja syntax):\n");
+    for (name, description) in TEMPLATE_VARIABLES {
+        help.push_str(&format!("  {:width$}  {}\n", name, description, width = width));
+    }
+    help
+}
+
+#[derive(Serialize)]
+struct TemplateHoliday<'a> {
+    date: NaiveDate,
+    name: &'a str,
+    local_name: Option<&'a str>,
+    display_name: String,
+    counties: Option<&'a [String]>,
+    types: &'a [String],
+    weekday: String,
+    days_until: i64,
+    is_weekend: bool,
+    is_past: bool,
+}
+
+// A --template file, checked for syntax errors when it is loaded
+#[derive(Debug, Clone)]
+pub struct ReportTemplate {
+    name: String,
+    source: String,
+}
+
+impl ReportTemplate {
+    pub fn load(path: &str) -> Result<ReportTemplate, String> {
+        let source = fs::read_to_string(path).map_err(|err| format!("could not read '{}': {}", path, err))?;
+        let template = ReportTemplate { name: path.to_string(), source };
+        template.environment().map_err(|err| describe_error(&err))?;
+        Ok(template)
+    }
+
+    fn environment(&self) -> Result<Environment<'_>, minijinja::Error> {
+        let mut environment = Environment::new();
+        environment.add_template(&self.name, &self.source)?;
+        Ok(environment)
+    }
+
+    pub fn render(&self, holidays: &[Holiday], config: &ListConfig) -> Result<String, String> {
+        let holidays: Vec<TemplateHoliday> = holidays
+            .iter()
+            .map(|holiday| {
+                TemplateHoliday {
+                    date: holiday.date,
+                    name: &holiday.name,
+                    local_name: holiday.local_name.as_deref(),
+                    display_name: holiday.display_name(config.language),
+                    counties: holiday.counties.as_deref(),
+                    types: &holiday.types,
+                    weekday: weekday_name(holiday.date, config.long_weekdays),
+                    days_until: (holiday.date - config.today).num_days(),
+                    is_weekend: matches!(holiday.date.weekday(), Weekday::Sat | Weekday::Sun),
+                    is_past: is_past(holiday, config.today),
+                }
+            })
+            .collect();
+
+        let environment = self.environment().map_err(|err| describe_error(&err))?;
+        let template = environment.get_template(&self.name).map_err(|err| describe_error(&err))?;
+        template
+            .render(context! {
+                country => config.country_code,
+                generated => config.today.to_string(),
+                holidays => holidays,
+            })
+            .map_err(|err| describe_error(&err))
+    }
+}
+
+// Errors name the file and line so they can be found in the template
+fn describe_error(err: &minijinja::Error) -> String {
+    match (err.name(), err.line()) {
+        (Some(name), Some(line)) => {
+            let detail = err.detail().map(str::to_string).unwrap_or_else(|| err.kind().to_string());
+            format!("{}:{}: {}", name, line, detail)
+        }
+        _ => err.to_string(),
+    }
+}
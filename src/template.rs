@@ -0,0 +1,106 @@
+use crate::output::weekday_name;
+use crate::{Holiday, ListConfig};
+
+// Placeholders that can be used in a --format template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    Date,
+    Name,
+    Counties,
+    Types,
+    Weekday,
+    DaysUntil,
+}
+
+const PLACEHOLDERS: [(&str, Placeholder); 6] = [
+    ("date", Placeholder::Date),
+    ("name", Placeholder::Name),
+    ("counties", Placeholder::Counties),
+    ("types", Placeholder::Types),
+    ("weekday", Placeholder::Weekday),
+    ("days_until", Placeholder::DaysUntil),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+// A parsed --format string such as "{date} {name}", literal braces are written as "{{" and "}}"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineTemplate {
+    parts: Vec<Part>,
+}
+
+impl LineTemplate {
+    pub fn parse(format: &str) -> Result<LineTemplate, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut characters = format.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            match character {
+                '{' if characters.peek() == Some(&'{') => {
+                    characters.next();
+                    literal.push('{');
+                }
+                '}' if characters.peek() == Some(&'}') => {
+                    characters.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match characters.next() {
+                            Some('}') => break,
+                            Some(character) => name.push(character),
+                            None => return Err(format!("unclosed placeholder '{{{}'", name)),
+                        }
+                    }
+                    let placeholder = PLACEHOLDERS
+                        .iter()
+                        .find(|(placeholder_name, _)| *placeholder_name == name)
+                        .map(|(_, placeholder)| *placeholder)
+                        .ok_or_else(|| {
+                            format!("unknown placeholder '{{{}}}'. Valid placeholders are: {}", name, valid_placeholders())
+                        })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => return Err("unmatched '}'. Use '}}' for a literal brace".to_string()),
+                character => literal.push(character),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(LineTemplate { parts })
+    }
+
+    pub fn render(&self, holiday: &Holiday, config: &ListConfig) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.clone(),
+                Part::Placeholder(Placeholder::Date) => holiday.date.to_string(),
+                Part::Placeholder(Placeholder::Name) => holiday.display_name(config.language),
+                Part::Placeholder(Placeholder::Counties) => holiday.counties_text(),
+                Part::Placeholder(Placeholder::Types) => holiday.types.join(", "),
+                Part::Placeholder(Placeholder::Weekday) => weekday_name(holiday.date, config.long_weekdays),
+                Part::Placeholder(Placeholder::DaysUntil) => (holiday.date - config.today).num_days().to_string(),
+            })
+            .collect()
+    }
+}
+
+fn valid_placeholders() -> String {
+    PLACEHOLDERS
+        .iter()
+        .map(|(name, _)| format!("{{{}}}", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,118 @@
+// A small HTTP server for the tests that request holidays. It runs on its own threads, so it answers
+// whatever runtime the test uses, and it keeps the requests so tests can check what was sent.
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+pub(crate) struct Request {
+    pub path: String, // With the query
+    pub headers: Vec<(String, String)>, // Names in lower case
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header == name).map(|(_, value)| value.as_str())
+    }
+}
+
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    pub fn json(body: &str) -> Self {
+        Response { status: 200, headers: vec![("Content-Type".to_string(), "application/json".to_string())], body: body.to_string() }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Response { status, headers: Vec::new(), body: String::new() }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub(crate) struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    pub fn start(respond: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("a local port is free");
+        let url = format!("http://{}", listener.local_addr().expect("the listener has an address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let respond = Arc::new(respond);
+        let server_requests = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (respond, requests) = (Arc::clone(&respond), Arc::clone(&server_requests));
+                std::thread::spawn(move || {
+                    if let Some(request) = read_request(&stream) {
+                        let response = respond(&request);
+                        requests.lock().unwrap_or_else(|err| err.into_inner()).push(request);
+                        write_response(stream, &response);
+                    }
+                });
+            }
+        });
+        TestServer { url, requests }
+    }
+
+    // The paths of the requests so far, in the order they came in
+    pub fn paths(&self) -> Vec<String> {
+        self.requests().iter().map(|request| request.path.clone()).collect()
+    }
+
+    pub fn requests(&self) -> std::sync::MutexGuard<'_, Vec<Request>> {
+        self.requests.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+// Only GET requests are made, so there is never a body to read
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut lines = BufReader::new(stream).lines();
+    let request_line = lines.next()?.ok()?;
+    let path = request_line.split_whitespace().nth(1)?.to_string();
+    let mut headers = Vec::new();
+    for line in lines {
+        let line = line.ok()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    Some(Request { path, headers })
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) {
+    let mut head = format!("HTTP/1.1 {} {}\r\nConnection: close\r\n", response.status, reason(response.status));
+    // A 204 or 304 has no body
+    if !matches!(response.status, 204 | 304) {
+        head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(response.body.as_bytes());
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        304 => "Not Modified",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}